@@ -0,0 +1,126 @@
+//! API key lookup, decoupled from the static `RUSTFS_API_KEYS_JSON` list.
+//!
+//! `auth_from_headers` used to index straight into an `Arc<HashMap<...>>`
+//! built once at startup, so rotating or revoking a key meant restarting the
+//! process. `ApiKeyProvider` is the seam: a static-file impl preserves that
+//! behavior, a SQLite-table impl lets an operator create/revoke keys at
+//! runtime, and `CompositeApiKeyProvider` checks the DB first so the two can
+//! be layered (env-configured keys as a bootstrap set, DB keys for anything
+//! created or revoked afterwards).
+
+use async_trait::async_trait;
+use rusqlite::{params, Connection};
+
+use crate::AppError;
+
+#[derive(Clone, Debug)]
+pub struct ApiKeyRecord {
+    pub tenant_id: String,
+    pub role: String,
+    pub disabled: bool,
+    pub expires_at_ms: Option<i64>,
+}
+
+impl ApiKeyRecord {
+    pub fn is_usable(&self, now_ms: i64) -> bool {
+        if self.disabled {
+            return false;
+        }
+        match self.expires_at_ms {
+            Some(exp) => exp > now_ms,
+            None => true,
+        }
+    }
+}
+
+#[async_trait]
+pub trait ApiKeyProvider: Send + Sync {
+    /// `key_hash` is the hex-encoded SHA-256 of the raw API key; callers
+    /// never hand providers the raw key itself.
+    async fn lookup(&self, key_hash: &str) -> Result<Option<ApiKeyRecord>, AppError>;
+}
+
+/// Preserves the original `RUSTFS_API_KEYS_JSON` behavior: keys are fixed at
+/// startup, indexed by the SHA-256 hash of each configured raw key.
+pub struct StaticApiKeyProvider {
+    keys: std::collections::HashMap<String, ApiKeyRecord>,
+}
+
+impl StaticApiKeyProvider {
+    pub fn new(keys: std::collections::HashMap<String, ApiKeyRecord>) -> Self {
+        Self { keys }
+    }
+}
+
+#[async_trait]
+impl ApiKeyProvider for StaticApiKeyProvider {
+    async fn lookup(&self, key_hash: &str) -> Result<Option<ApiKeyRecord>, AppError> {
+        Ok(self.keys.get(key_hash).cloned())
+    }
+}
+
+/// Backs API keys with the `api_keys` table so they can be created, expired,
+/// and revoked without a restart.
+pub struct SqliteApiKeyProvider {
+    db_path: std::path::PathBuf,
+}
+
+impl SqliteApiKeyProvider {
+    pub fn new(db_path: std::path::PathBuf) -> Self {
+        Self { db_path }
+    }
+}
+
+#[async_trait]
+impl ApiKeyProvider for SqliteApiKeyProvider {
+    async fn lookup(&self, key_hash: &str) -> Result<Option<ApiKeyRecord>, AppError> {
+        let db_path = self.db_path.clone();
+        let key_hash = key_hash.to_string();
+        tokio::task::spawn_blocking(move || -> Result<Option<ApiKeyRecord>, AppError> {
+            let conn = Connection::open(db_path).map_err(|e| AppError::Db(e.to_string()))?;
+            let mut stmt = conn
+                .prepare(
+                    "SELECT tenant_id, role, disabled, expires_at_ms FROM api_keys WHERE key_hash=?1",
+                )
+                .map_err(|e| AppError::Db(e.to_string()))?;
+            let mut rows = stmt
+                .query(params![key_hash])
+                .map_err(|e| AppError::Db(e.to_string()))?;
+            if let Some(row) = rows.next().map_err(|e| AppError::Db(e.to_string()))? {
+                let disabled: i64 = row.get(2).map_err(|e| AppError::Db(e.to_string()))?;
+                return Ok(Some(ApiKeyRecord {
+                    tenant_id: row.get(0).map_err(|e| AppError::Db(e.to_string()))?,
+                    role: row.get(1).map_err(|e| AppError::Db(e.to_string()))?,
+                    disabled: disabled != 0,
+                    expires_at_ms: row.get(3).map_err(|e| AppError::Db(e.to_string()))?,
+                }));
+            }
+            Ok(None)
+        })
+        .await
+        .map_err(|e| AppError::Db(e.to_string()))?
+    }
+}
+
+/// Checks `primary` (the SQLite provider) first, then falls back to
+/// `fallback` (the static/env-configured provider) when a key isn't found.
+pub struct CompositeApiKeyProvider {
+    primary: SqliteApiKeyProvider,
+    fallback: StaticApiKeyProvider,
+}
+
+impl CompositeApiKeyProvider {
+    pub fn new(primary: SqliteApiKeyProvider, fallback: StaticApiKeyProvider) -> Self {
+        Self { primary, fallback }
+    }
+}
+
+#[async_trait]
+impl ApiKeyProvider for CompositeApiKeyProvider {
+    async fn lookup(&self, key_hash: &str) -> Result<Option<ApiKeyRecord>, AppError> {
+        if let Some(record) = self.primary.lookup(key_hash).await? {
+            return Ok(Some(record));
+        }
+        self.fallback.lookup(key_hash).await
+    }
+}
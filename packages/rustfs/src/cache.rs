@@ -0,0 +1,117 @@
+//! Bounded in-memory LRU cache for already-decrypted download bytes, keyed
+//! by `file_id`.
+//!
+//! `download`/`public_download` re-read (and, for passphrase-encrypted
+//! files, re-decrypt) the same blob from disk on every hit. For a
+//! read-heavy deployment where a handful of files account for most
+//! requests, caching the plaintext once and serving it straight from memory
+//! on subsequent hits avoids repeating that disk and crypto cost. Only
+//! whole-file (non-`Range`) responses are cached, since a partial decrypt
+//! done to satisfy one `Range` request isn't valid to hand back as if it
+//! were the whole file.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use bytes::Bytes;
+
+/// What gets served back on a cache hit. Just the decrypted bytes: the
+/// response headers (`Content-Type` included) are always rebuilt from the
+/// same DB row lookup that precedes the cache check, so there's nothing
+/// else worth caching alongside them.
+#[derive(Clone)]
+pub struct CachedDownload {
+    pub bytes: Bytes,
+}
+
+struct Entry {
+    value: CachedDownload,
+    size: usize,
+    last_used: u64,
+}
+
+struct Inner {
+    entries: HashMap<String, Entry>,
+    total_bytes: usize,
+    clock: u64,
+}
+
+/// Bounded by both total bytes and entry count; whichever limit is hit
+/// first triggers eviction of the least-recently-used entry.
+pub struct DownloadCache {
+    inner: Mutex<Inner>,
+    max_bytes: usize,
+    max_entries: usize,
+}
+
+impl DownloadCache {
+    pub fn new(max_bytes: usize, max_entries: usize) -> Self {
+        Self {
+            inner: Mutex::new(Inner {
+                entries: HashMap::new(),
+                total_bytes: 0,
+                clock: 0,
+            }),
+            max_bytes,
+            max_entries,
+        }
+    }
+
+    /// `false` when either budget is configured to zero, i.e. the cache is
+    /// turned off entirely (the default, to keep `download`/`public_download`
+    /// purely streaming unless an operator opts in).
+    pub fn is_enabled(&self) -> bool {
+        self.max_bytes > 0 && self.max_entries > 0
+    }
+
+    pub fn get(&self, file_id: &str) -> Option<CachedDownload> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.clock += 1;
+        let clock = inner.clock;
+        let entry = inner.entries.get_mut(file_id)?;
+        entry.last_used = clock;
+        Some(entry.value.clone())
+    }
+
+    pub fn insert(&self, file_id: String, value: CachedDownload) {
+        if !self.is_enabled() {
+            return;
+        }
+        let size = value.bytes.len();
+        if size > self.max_bytes {
+            return;
+        }
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(old) = inner.entries.remove(&file_id) {
+            inner.total_bytes -= old.size;
+        }
+        while !inner.entries.is_empty()
+            && (inner.total_bytes + size > self.max_bytes || inner.entries.len() >= self.max_entries)
+        {
+            let lru_key = inner
+                .entries
+                .iter()
+                .min_by_key(|(_, e)| e.last_used)
+                .map(|(k, _)| k.clone());
+            match lru_key {
+                Some(key) => {
+                    if let Some(removed) = inner.entries.remove(&key) {
+                        inner.total_bytes -= removed.size;
+                    }
+                }
+                None => break,
+            }
+        }
+        inner.clock += 1;
+        let clock = inner.clock;
+        inner.total_bytes += size;
+        inner.entries.insert(file_id, Entry { value, size, last_used: clock });
+    }
+
+    pub fn invalidate(&self, file_id: &str) {
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(removed) = inner.entries.remove(file_id) {
+            inner.total_bytes -= removed.size;
+        }
+    }
+}
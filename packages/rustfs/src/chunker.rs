@@ -0,0 +1,101 @@
+//! Content-defined chunking for block-level dedup.
+//!
+//! Whole-file dedup (`file_id = sha256(file)`) misses near-duplicate
+//! uploads: a 1 GiB file with one changed byte is stored twice in full.
+//! `Chunker` splits a byte stream into content-defined chunks using a
+//! rolling hash over a sliding window, so a local edit only shifts the
+//! chunk boundaries immediately around it and every other chunk keeps its
+//! existing hash and can be deduplicated via the shared chunk store.
+//!
+//! The rolling hash here is a buzhash (cyclic-polynomial) variant rather
+//! than a true Rabin fingerprint: it gives the same content-defined
+//! boundary property (same local window -> same hash, independent of
+//! position) with cheaper per-byte work, and is what a number of
+//! production chunkers (e.g. casync, bup) use in place of Rabin.
+
+use std::collections::VecDeque;
+use std::sync::OnceLock;
+
+/// Sliding window size, in bytes, the rolling hash is computed over.
+const WINDOW: usize = 48;
+/// Boundary when `hash & MASK == 0`; chosen for a ~1 MiB average chunk size.
+const BOUNDARY_MASK: u64 = (1 << 20) - 1;
+/// Never emit a chunk smaller than this (except the final, possibly-short one).
+pub const MIN_CHUNK_SIZE: usize = 256 * 1024;
+/// Force a boundary if a chunk grows past this, regardless of the hash.
+pub const MAX_CHUNK_SIZE: usize = 4 * 1024 * 1024;
+
+fn buzhash_table() -> &'static [u64; 256] {
+    static TABLE: OnceLock<[u64; 256]> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        // Deterministic xorshift64* stream; this only needs to look random
+        // to the hash, not be cryptographically secure.
+        let mut table = [0u64; 256];
+        let mut seed: u64 = 0x9E3779B97F4A7C15;
+        for slot in table.iter_mut() {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            *slot = seed.wrapping_mul(0x2545_F491_4F6C_DD1D);
+        }
+        table
+    })
+}
+
+/// Splits a byte stream into content-defined chunks as bytes are fed in.
+pub struct Chunker {
+    table: &'static [u64; 256],
+    window: VecDeque<u8>,
+    hash: u64,
+    current: Vec<u8>,
+}
+
+impl Chunker {
+    pub fn new() -> Self {
+        Self {
+            table: buzhash_table(),
+            window: VecDeque::with_capacity(WINDOW),
+            hash: 0,
+            current: Vec::new(),
+        }
+    }
+
+    /// Feeds more bytes in, returning any chunks completed as a result.
+    pub fn push(&mut self, data: &[u8]) -> Vec<Vec<u8>> {
+        let mut completed = Vec::new();
+        for &byte in data {
+            self.current.push(byte);
+            if self.window.len() == WINDOW {
+                let leaving = self.window.pop_front().expect("window at capacity");
+                self.hash ^= self.table[leaving as usize].rotate_left(WINDOW as u32 - 1);
+            }
+            self.window.push_back(byte);
+            self.hash = self.hash.rotate_left(1) ^ self.table[byte as usize];
+
+            let at_content_boundary =
+                self.current.len() >= MIN_CHUNK_SIZE && (self.hash & BOUNDARY_MASK) == 0;
+            let at_forced_boundary = self.current.len() >= MAX_CHUNK_SIZE;
+            if at_content_boundary || at_forced_boundary {
+                completed.push(std::mem::take(&mut self.current));
+                self.hash = 0;
+                self.window.clear();
+            }
+        }
+        completed
+    }
+
+    /// Flushes whatever partial chunk remains once the stream is exhausted.
+    pub fn finish(&mut self) -> Option<Vec<u8>> {
+        if self.current.is_empty() {
+            None
+        } else {
+            Some(std::mem::take(&mut self.current))
+        }
+    }
+}
+
+impl Default for Chunker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,305 @@
+//! ed25519 HTTP Signature authentication, an alternative to API keys for
+//! machine clients where the secret should never transit the wire.
+//!
+//! A tenant registers an ed25519 public key (via `RUSTFS_TENANT_PUBKEYS_JSON`,
+//! parsed much like `parse_api_keys_json`/`parse_tenant_identities_json`) and
+//! signs each request with the matching private key. The `Signature` header
+//! names which headers were covered; the server reconstructs the same
+//! signing string from the request it received and verifies it against the
+//! tenant's public key, so a request can only be accepted if the caller
+//! holds that private key and nothing it claims to cover was altered in
+//! transit.
+
+use std::collections::HashMap;
+
+use axum::http::HeaderMap;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine as _;
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use sha2::{Digest, Sha256};
+
+use crate::AppError;
+
+/// Requests more than this far from the server clock (in either direction)
+/// are rejected, so a captured `(request-target, host, date, digest)` tuple
+/// can't be replayed indefinitely.
+const DATE_SKEW_MS: i64 = 300_000;
+
+/// Parses `RUSTFS_TENANT_PUBKEYS_JSON` (`tenant_id -> base64 ed25519 public
+/// key`) into a map, ready for `verify`. Malformed entries are dropped
+/// rather than failing startup, matching `parse_tenant_identities_json`.
+pub fn parse_tenant_pubkeys_json(raw: &str) -> HashMap<String, String> {
+    let trimmed = raw.trim();
+    if trimmed.is_empty() {
+        return HashMap::new();
+    }
+    let parsed: serde_json::Value = match serde_json::from_str(trimmed) {
+        Ok(v) => v,
+        Err(_) => return HashMap::new(),
+    };
+    let mut map = HashMap::new();
+    if let Some(obj) = parsed.as_object() {
+        for (tenant_id, pubkey) in obj {
+            if let Some(pubkey) = pubkey.as_str() {
+                map.insert(tenant_id.trim().to_string(), pubkey.trim().to_string());
+            }
+        }
+    }
+    map
+}
+
+/// The parsed contents of a `Signature: keyId="...",algorithm="ed25519",headers="...",signature="..."` header.
+struct SignatureHeader {
+    key_id: String,
+    algorithm: String,
+    headers: Vec<String>,
+    signature: Vec<u8>,
+}
+
+fn parse_signature_header(raw: &str) -> Result<SignatureHeader, AppError> {
+    let mut key_id = None;
+    let mut algorithm = None;
+    let mut headers = None;
+    let mut signature = None;
+
+    for field in raw.split(',') {
+        let field = field.trim();
+        let (name, value) = field
+            .split_once('=')
+            .ok_or_else(|| AppError::Unauthorized)?;
+        let value = value.trim().trim_matches('"');
+        match name {
+            "keyId" => key_id = Some(value.to_string()),
+            "algorithm" => algorithm = Some(value.to_string()),
+            "headers" => headers = Some(value.split(' ').map(|s| s.trim().to_lowercase()).collect()),
+            "signature" => {
+                signature = Some(
+                    STANDARD
+                        .decode(value)
+                        .map_err(|_| AppError::Unauthorized)?,
+                )
+            }
+            _ => {}
+        }
+    }
+
+    Ok(SignatureHeader {
+        key_id: key_id.ok_or(AppError::Unauthorized)?,
+        algorithm: algorithm.unwrap_or_else(|| "ed25519".to_string()),
+        headers: headers.ok_or(AppError::Unauthorized)?,
+        signature: signature.ok_or(AppError::Unauthorized)?,
+    })
+}
+
+/// Verifies an ed25519-signed request and returns the signing tenant's id on
+/// success. `method`/`request_target` reconstruct the `(request-target)`
+/// pseudo-header; `headers`/`body` are the request's actual headers and
+/// (fully buffered) body, used both to rebuild the signing string and to
+/// check the claimed `digest` header against the body that was actually
+/// received.
+pub fn verify(
+    pubkeys: &HashMap<String, String>,
+    method: &str,
+    request_target: &str,
+    headers: &HeaderMap,
+    body: &[u8],
+) -> Result<String, AppError> {
+    let raw_signature = headers
+        .get("signature")
+        .and_then(|v| v.to_str().ok())
+        .ok_or(AppError::Unauthorized)?;
+    let sig_header = parse_signature_header(raw_signature)?;
+    if sig_header.algorithm != "ed25519" {
+        return Err(AppError::Unauthorized);
+    }
+
+    let pubkey_b64 = pubkeys.get(&sig_header.key_id).ok_or(AppError::Unauthorized)?;
+    let pubkey_bytes: [u8; 32] = STANDARD
+        .decode(pubkey_b64)
+        .map_err(|_| AppError::Unauthorized)?
+        .try_into()
+        .map_err(|_| AppError::Unauthorized)?;
+    let verifying_key = VerifyingKey::from_bytes(&pubkey_bytes).map_err(|_| AppError::Unauthorized)?;
+    let signature = Signature::from_slice(&sig_header.signature).map_err(|_| AppError::Unauthorized)?;
+
+    // These three (`digest` only when the body is non-empty) are mandated by
+    // the server, not left to the client's `headers=` list: otherwise a signature that simply omits one from its
+    // claimed coverage would skip the corresponding check below, enabling
+    // replay (no `date` bound), body tampering (no `digest` bound), or
+    // cross-endpoint/method replay (no `(request-target)` bound) for any
+    // signature ever captured off the wire. `sig_header.headers` is already
+    // lower-cased in `parse_signature_header`, matching `HeaderMap::get`'s
+    // case-insensitive lookup.
+    if !sig_header.headers.iter().any(|h| h == "(request-target)") {
+        return Err(AppError::Unauthorized);
+    }
+    if !sig_header.headers.iter().any(|h| h == "date") {
+        return Err(AppError::Unauthorized);
+    }
+    if !body.is_empty() && !sig_header.headers.iter().any(|h| h == "digest") {
+        return Err(AppError::Unauthorized);
+    }
+
+    {
+        let date_header = headers
+            .get("date")
+            .and_then(|v| v.to_str().ok())
+            .ok_or(AppError::Unauthorized)?;
+        let date_ms = httpdate::parse_http_date(date_header)
+            .map_err(|_| AppError::Unauthorized)?
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_err(|_| AppError::Unauthorized)?
+            .as_millis() as i64;
+        if (crate::now_ms() - date_ms).abs() > DATE_SKEW_MS {
+            return Err(AppError::Unauthorized);
+        }
+    }
+
+    if sig_header.headers.iter().any(|h| h == "digest") {
+        let digest_header = headers
+            .get("digest")
+            .and_then(|v| v.to_str().ok())
+            .ok_or(AppError::Unauthorized)?;
+        let claimed = digest_header
+            .strip_prefix("SHA-256=")
+            .ok_or(AppError::Unauthorized)?;
+        let mut hasher = Sha256::new();
+        hasher.update(body);
+        let actual = STANDARD.encode(hasher.finalize());
+        if claimed != actual {
+            return Err(AppError::Unauthorized);
+        }
+    }
+
+    let mut signing_string_lines = Vec::with_capacity(sig_header.headers.len());
+    for name in &sig_header.headers {
+        let line = if name == "(request-target)" {
+            format!("(request-target): {} {}", method.to_lowercase(), request_target)
+        } else {
+            let value = headers
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .ok_or(AppError::Unauthorized)?;
+            format!("{name}: {value}")
+        };
+        signing_string_lines.push(line);
+    }
+    let signing_string = signing_string_lines.join("\n");
+
+    verifying_key
+        .verify(signing_string.as_bytes(), &signature)
+        .map_err(|_| AppError::Unauthorized)?;
+
+    Ok(sig_header.key_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+    use ed25519_dalek::{Signer, SigningKey};
+
+    fn build_request(
+        signing_key: &SigningKey,
+        method: &str,
+        request_target: &str,
+        date: &str,
+        body: &[u8],
+        covered_headers: &[&str],
+    ) -> HeaderMap {
+        let digest = {
+            let mut hasher = Sha256::new();
+            hasher.update(body);
+            format!("SHA-256={}", STANDARD.encode(hasher.finalize()))
+        };
+        let lines: Vec<String> = covered_headers
+            .iter()
+            .map(|name| match *name {
+                "(request-target)" => format!("(request-target): {} {}", method.to_lowercase(), request_target),
+                "date" => format!("date: {date}"),
+                "digest" => format!("digest: {digest}"),
+                other => panic!("unsupported header in test helper: {other}"),
+            })
+            .collect();
+        let signature = signing_key.sign(lines.join("\n").as_bytes());
+
+        let mut headers = HeaderMap::new();
+        headers.insert("date", HeaderValue::from_str(date).unwrap());
+        headers.insert("digest", HeaderValue::from_str(&digest).unwrap());
+        let sig_header = format!(
+            "keyId=\"test\",algorithm=\"ed25519\",headers=\"{}\",signature=\"{}\"",
+            covered_headers.join(" "),
+            STANDARD.encode(signature.to_bytes()),
+        );
+        headers.insert("signature", HeaderValue::from_str(&sig_header).unwrap());
+        headers
+    }
+
+    fn pubkeys(signing_key: &SigningKey) -> HashMap<String, String> {
+        let mut map = HashMap::new();
+        map.insert("test".to_string(), STANDARD.encode(signing_key.verifying_key().to_bytes()));
+        map
+    }
+
+    #[test]
+    fn accepts_a_fully_covered_signature() {
+        let signing_key = SigningKey::generate(&mut rand::rngs::OsRng);
+        let date = httpdate::fmt_http_date(std::time::SystemTime::now());
+        let body = b"hello world";
+        let headers = build_request(&signing_key, "POST", "/v1/files", &date, body, &["(request-target)", "date", "digest"]);
+        let tenant_id = verify(&pubkeys(&signing_key), "POST", "/v1/files", &headers, body).unwrap();
+        assert_eq!(tenant_id, "test");
+    }
+
+    #[test]
+    fn rejects_a_signature_that_omits_digest_from_its_own_coverage() {
+        let signing_key = SigningKey::generate(&mut rand::rngs::OsRng);
+        let date = httpdate::fmt_http_date(std::time::SystemTime::now());
+        let body = b"hello world";
+        let headers = build_request(&signing_key, "POST", "/v1/files", &date, body, &["(request-target)", "date"]);
+        assert!(verify(&pubkeys(&signing_key), "POST", "/v1/files", &headers, body).is_err());
+    }
+
+    #[test]
+    fn rejects_a_signature_that_omits_date_from_its_own_coverage() {
+        let signing_key = SigningKey::generate(&mut rand::rngs::OsRng);
+        let date = httpdate::fmt_http_date(std::time::SystemTime::now());
+        let body = b"hello world";
+        let headers = build_request(&signing_key, "POST", "/v1/files", &date, body, &["(request-target)", "digest"]);
+        assert!(verify(&pubkeys(&signing_key), "POST", "/v1/files", &headers, body).is_err());
+    }
+
+    #[test]
+    fn rejects_a_signature_that_omits_request_target_from_its_own_coverage() {
+        let signing_key = SigningKey::generate(&mut rand::rngs::OsRng);
+        let date = httpdate::fmt_http_date(std::time::SystemTime::now());
+        let body = b"hello world";
+        let headers = build_request(&signing_key, "POST", "/v1/files", &date, body, &["date", "digest"]);
+        assert!(verify(&pubkeys(&signing_key), "POST", "/v1/files", &headers, body).is_err());
+    }
+
+    #[test]
+    fn header_coverage_matching_is_case_insensitive() {
+        let signing_key = SigningKey::generate(&mut rand::rngs::OsRng);
+        let date = httpdate::fmt_http_date(std::time::SystemTime::now());
+        let body = b"hello world";
+        let headers = build_request(&signing_key, "POST", "/v1/files", &date, body, &["(request-target)", "date", "digest"]);
+        let raw = headers.get("signature").unwrap().to_str().unwrap().to_string();
+        let mixed_case = raw.replace(
+            "headers=\"(request-target) date digest\"",
+            "headers=\"(request-target) Date Digest\"",
+        );
+        let mut headers = headers;
+        headers.insert("signature", HeaderValue::from_str(&mixed_case).unwrap());
+        assert!(verify(&pubkeys(&signing_key), "POST", "/v1/files", &headers, body).is_ok());
+    }
+
+    #[test]
+    fn rejects_a_tampered_body_against_its_digest() {
+        let signing_key = SigningKey::generate(&mut rand::rngs::OsRng);
+        let date = httpdate::fmt_http_date(std::time::SystemTime::now());
+        let body = b"hello world";
+        let headers = build_request(&signing_key, "POST", "/v1/files", &date, body, &["(request-target)", "date", "digest"]);
+        assert!(verify(&pubkeys(&signing_key), "POST", "/v1/files", &headers, b"goodbye world").is_err());
+    }
+}
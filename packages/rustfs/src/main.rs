@@ -1,17 +1,19 @@
 use std::{
     collections::HashMap,
-    io::Read,
+    io::{Read, Write},
     iter,
+    net::SocketAddr,
     path::{Path, PathBuf},
     sync::Arc,
     time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
-use age::secrecy::SecretString;
+use age::secrecy::{ExposeSecret, SecretString};
 use axum::{
     body::Body,
-    extract::{Multipart, Query, State},
+    extract::{ConnectInfo, DefaultBodyLimit, MatchedPath, Multipart, Query, Request, State},
     http::{header, HeaderMap, StatusCode},
+    middleware::{self, Next},
     response::{IntoResponse, Response},
     routing::{get, post},
     Json, Router,
@@ -20,36 +22,117 @@ use base64::engine::general_purpose::URL_SAFE_NO_PAD;
 use base64::Engine as _;
 use bytes::Bytes;
 use hmac::{Hmac, Mac};
-use rusqlite::{params, Connection};
+use metrics_exporter_prometheus::{PrometheusBuilder, PrometheusHandle};
+use rusqlite::{params, Connection, OptionalExtension};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use thiserror::Error;
 use tokio::{fs, net::TcpListener};
 use tokio_stream::wrappers::ReceiverStream;
 use tokio_util::io::ReaderStream;
+use tower::ServiceBuilder;
+use tower_http::compression::CompressionLayer;
+use tower_http::cors::{Any, CorsLayer};
+use tower_http::limit::RequestBodyLimitLayer;
 use tower_http::trace::TraceLayer;
 use tracing::{info, warn};
 use tokio::io::AsyncWriteExt;
 
+mod auth_provider;
+mod cache;
+mod chunker;
+mod crypto;
+mod http_sig;
+mod storage;
+mod tls;
+use auth_provider::{ApiKeyProvider, ApiKeyRecord};
+use cache::DownloadCache;
+use storage::StorageBackend;
+
+/// Pseudo-tenant the chunk store is namespaced under in the storage backend,
+/// since chunks are deduplicated across tenants by content hash.
+const CHUNK_TENANT: &str = "_chunks";
+
+/// Pseudo-tenant the content-addressed blob store is namespaced under, for
+/// the same reason as `CHUNK_TENANT`: blobs are shared across tenants by
+/// content hash rather than belonging to any one tenant's storage area.
+const BLOB_TENANT: &str = "_blobs";
+
+/// Body-size cap applied when `RUSTFS_MAX_UPLOAD_BYTES` is unset, matching
+/// axum's own `DefaultBodyLimit` default. Used by `verify_http_signature`,
+/// which reads the whole body itself and must never buffer more than that
+/// regardless of which route it's guarding. The per-route
+/// `RequestBodyLimitLayer` is only installed when `RUSTFS_MAX_UPLOAD_BYTES`
+/// is set; otherwise axum's own (equal) default limit stays in effect.
+const DEFAULT_MAX_UPLOAD_BYTES: usize = 2 * 1024 * 1024;
+
+/// Content-addressed storage key for a blob, derived from its plaintext
+/// sha256 (fan-out into two levels of subdirectories so no single directory
+/// ends up with millions of entries, as e.g. git and casync do).
+fn blob_key(sha256: &str) -> String {
+    format!("blobs/{}/{}/{sha256}.age", &sha256[0..2], &sha256[2..4])
+}
+
+/// The master key(s) used to wrap per-file data keys (see `crypto::wrap_data_key`).
+/// `current_version` is stamped onto every newly-wrapped data key; `retired`
+/// holds prior versions so files wrapped under them stay decryptable until
+/// `rotate_key` has rewrapped everything onto the new current key.
+#[derive(Clone)]
+struct MasterKeyRing {
+    current_version: i64,
+    current: SecretString,
+    retired: HashMap<i64, SecretString>,
+}
+
+impl MasterKeyRing {
+    fn new(key: SecretString) -> Self {
+        Self {
+            current_version: 1,
+            current: key,
+            retired: HashMap::new(),
+        }
+    }
+
+    fn key_for_version(&self, version: i64) -> Option<SecretString> {
+        if version == self.current_version {
+            Some(self.current.clone())
+        } else {
+            self.retired.get(&version).cloned()
+        }
+    }
+}
+
 #[derive(Clone)]
 struct AppState {
     data_dir: PathBuf,
     db_path: PathBuf,
     require_api_key: bool,
-    api_keys: Arc<HashMap<String, ApiKey>>,
-    master_key: Option<SecretString>,
+    api_key_provider: Arc<dyn ApiKeyProvider>,
+    master_keys: Option<Arc<tokio::sync::RwLock<MasterKeyRing>>>,
     signing_key: Option<Vec<u8>>,
     public_base_url: Option<String>,
     audit_log_path: Option<PathBuf>,
-}
-
-#[derive(Clone, Debug, Deserialize)]
-struct ApiKey {
-    #[allow(dead_code)]
-    key: String,
-    tenant_id: String,
-    #[allow(dead_code)]
-    role: Option<String>,
+    storage: Arc<dyn StorageBackend>,
+    /// Server-held age identities for tenants using recipient encryption,
+    /// keyed by tenant_id, loaded from `RUSTFS_TENANT_IDENTITIES_JSON`.
+    tenant_identities: Arc<HashMap<String, String>>,
+    /// Renders the metrics the `track_metrics` middleware and `/metrics`
+    /// handler record into, as Prometheus text format.
+    metrics_handle: PrometheusHandle,
+    /// Registered ed25519 public keys for HTTP Signature auth, keyed by
+    /// tenant_id, loaded from `RUSTFS_TENANT_PUBKEYS_JSON`.
+    tenant_pubkeys: Arc<HashMap<String, String>>,
+    /// Bounded cache of already-decrypted whole-file downloads, keyed by
+    /// `file_id`. Disabled (a pure pass-through) unless `RUSTFS_CACHE_MAX_BYTES`
+    /// and `RUSTFS_CACHE_MAX_ENTRIES` are both configured to a positive value.
+    download_cache: Arc<DownloadCache>,
+    /// `RUSTFS_MAX_UPLOAD_BYTES`, or `DEFAULT_MAX_UPLOAD_BYTES` if unset.
+    /// `verify_http_signature` buffers the whole body itself (to check the
+    /// signed `digest`) ahead of the per-route `RequestBodyLimitLayer`, so it
+    /// needs this to enforce the same cap itself rather than reading an
+    /// unbounded body for any request that merely carries a `signature`
+    /// header.
+    max_upload_bytes: usize,
 }
 
 #[derive(Clone, Debug)]
@@ -57,6 +140,10 @@ struct AuthContext {
     tenant_id: String,
     role: String,
     key_id: String,
+    /// `None` for a static API key or the no-auth dev mode, meaning "whatever
+    /// `role` allows". `Some` for a minted `/v1/tokens` bearer token, which
+    /// narrows that further to just these scopes (see `assert_scope`).
+    scopes: Option<Vec<String>>,
 }
 
 #[derive(Debug, Serialize)]
@@ -113,6 +200,17 @@ struct IngestResponse {
 #[derive(Debug, Deserialize)]
 struct LinkRequest {
     ttl_seconds: Option<u32>,
+    /// Caps how many times the token can be redeemed; omitted means
+    /// unlimited (subject only to `exp_ms` and revocation).
+    max_uses: Option<u32>,
+    /// If set, `public_download` rejects any request not made from this
+    /// exact peer address.
+    allowed_ip: Option<String>,
+    /// What the token is allowed to do; currently only `"download"` is
+    /// checked, but the set is carried so future endpoints (e.g. a `"meta"`
+    /// capability for metadata-only links) can gate on it without a token
+    /// format change.
+    capabilities: Option<Vec<String>>,
 }
 
 #[derive(Debug, Serialize)]
@@ -122,6 +220,7 @@ struct LinkResponse {
     path: String,
     url: Option<String>,
     expires_at_ms: i64,
+    jti: String,
 }
 
 #[derive(Debug, Deserialize)]
@@ -152,6 +251,8 @@ enum AppError {
     Db(String),
     #[error("crypto: {0}")]
     Crypto(String),
+    #[error("payload_too_large: {0}")]
+    PayloadTooLarge(String),
 }
 
 impl IntoResponse for AppError {
@@ -185,6 +286,13 @@ impl IntoResponse for AppError {
                     message: None,
                 },
             ),
+            AppError::PayloadTooLarge(msg) => (
+                StatusCode::PAYLOAD_TOO_LARGE,
+                ErrorBody {
+                    error: "payload_too_large",
+                    message: Some(msg.clone()),
+                },
+            ),
             _ => (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 ErrorBody {
@@ -204,11 +312,16 @@ fn now_ms() -> i64 {
         .as_millis() as i64
 }
 
+/// `role` omitted or misspelled defaults to the least-privileged role rather
+/// than `"admin"`: `POST /v1/admin/api_keys` with no `role` field is an
+/// ordinary, easy-to-make request, and silently minting a full admin key for
+/// it would be a one-typo path to cross-tenant super-admin control of the
+/// whole deployment.
 fn normalize_role(role: Option<&str>) -> String {
-    let r = role.unwrap_or("admin").trim().to_lowercase();
+    let r = role.unwrap_or("reader").trim().to_lowercase();
     match r.as_str() {
         "reader" | "writer" | "admin" => r,
-        _ => "admin".to_string(),
+        _ => "reader".to_string(),
     }
 }
 
@@ -219,11 +332,38 @@ fn key_id_from_raw(raw_key: &str) -> String {
     hex::encode(&digest[..8])
 }
 
-fn auth_from_headers(
+/// Full SHA-256 of a raw API key, hex-encoded. This (not the raw key) is
+/// what `ApiKeyProvider`s index and persist, and what `POST
+/// /v1/admin/api_keys` stores in `api_keys.key_hash`.
+fn full_key_hash(raw_key: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(raw_key.as_bytes());
+    hex::encode(hasher.finalize())
+}
+
+/// Header `verify_http_signature` stamps onto a request, naming the tenant a
+/// valid ed25519 HTTP Signature was verified for. Never set by a client: the
+/// middleware strips any incoming value before it does its own verification,
+/// so `auth_from_headers` can trust its presence here unconditionally.
+const SIG_AUTH_TENANT_HEADER: &str = "x-rustfs-verified-sig-tenant";
+
+async fn auth_from_headers(
     state: &AppState,
     headers: &HeaderMap,
     tenant_hint: Option<&str>,
 ) -> Result<AuthContext, AppError> {
+    if let Some(tenant_id) = headers
+        .get(SIG_AUTH_TENANT_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.to_string())
+    {
+        return Ok(AuthContext {
+            tenant_id,
+            role: "writer".to_string(),
+            key_id: "http-signature".to_string(),
+            scopes: None,
+        });
+    }
     if !state.require_api_key {
         if let Some(t) = tenant_hint {
             let trimmed = t.trim();
@@ -232,6 +372,7 @@ fn auth_from_headers(
                     tenant_id: trimmed.to_string(),
                     role: "admin".to_string(),
                     key_id: "dev".to_string(),
+                    scopes: None,
                 });
             }
         }
@@ -239,20 +380,63 @@ fn auth_from_headers(
             tenant_id: "default".to_string(),
             role: "admin".to_string(),
             key_id: "dev".to_string(),
+            scopes: None,
         });
     }
-    let key = headers
+    if let Some(key) = headers
         .get("x-api-key")
         .and_then(|v| v.to_str().ok())
         .map(|s| s.trim().to_string())
         .filter(|s| !s.is_empty())
-        .ok_or(AppError::Unauthorized)?;
-    let entry = state.api_keys.get(&key).ok_or(AppError::Unauthorized)?;
-    Ok(AuthContext {
-        tenant_id: entry.tenant_id.clone(),
-        role: normalize_role(entry.role.as_deref()),
-        key_id: key_id_from_raw(&key),
-    })
+    {
+        let entry = state
+            .api_key_provider
+            .lookup(&full_key_hash(&key))
+            .await?
+            .ok_or(AppError::Unauthorized)?;
+        if !entry.is_usable(now_ms()) {
+            return Err(AppError::Unauthorized);
+        }
+        return Ok(AuthContext {
+            tenant_id: entry.tenant_id,
+            role: normalize_role(Some(&entry.role)),
+            key_id: key_id_from_raw(&key),
+            scopes: None,
+        });
+    }
+    if let Some(token) = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+    {
+        let signing_key = state.signing_key.as_deref().ok_or(AppError::Unauthorized)?;
+        let payload = verify_scoped_token(signing_key, &token)?;
+        return Ok(AuthContext {
+            tenant_id: payload.tenant_id,
+            role: role_for_scopes(&payload.scopes),
+            key_id: key_id_from_raw(&token),
+            scopes: Some(payload.scopes),
+        });
+    }
+    Err(AppError::Unauthorized)
+}
+
+/// The coarse role a scoped bearer token should be treated as for the
+/// `assert_can_read`/`assert_can_write`/`assert_is_admin` role gate, before
+/// `assert_scope` narrows it to the token's actual scope list. A token that
+/// grants no write-ish scope never clears the `assert_can_write` gate
+/// regardless of this role, so this only needs to be permissive enough to
+/// reach the right gate, not precise.
+fn role_for_scopes(scopes: &[String]) -> String {
+    if scopes.iter().any(|s| s == "admin") {
+        "admin".to_string()
+    } else if scopes.iter().any(|s| s == "ingest") {
+        "writer".to_string()
+    } else {
+        "reader".to_string()
+    }
 }
 
 async fn init_db(db_path: &Path) -> Result<(), AppError> {
@@ -283,6 +467,49 @@ CREATE TABLE IF NOT EXISTS files (
 CREATE INDEX IF NOT EXISTS idx_files_tenant_created ON files(tenant_id, created_at_ms DESC);
 CREATE INDEX IF NOT EXISTS idx_files_tenant_session ON files(tenant_id, session_id);
 CREATE INDEX IF NOT EXISTS idx_files_tenant_filename ON files(tenant_id, filename);
+CREATE TABLE IF NOT EXISTS chunks (
+  sha256 TEXT PRIMARY KEY,
+  size INTEGER NOT NULL,
+  refcount INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS file_chunks (
+  tenant_id TEXT NOT NULL,
+  file_id TEXT NOT NULL,
+  idx INTEGER NOT NULL,
+  chunk_sha256 TEXT NOT NULL,
+  PRIMARY KEY (tenant_id, file_id, idx)
+);
+CREATE INDEX IF NOT EXISTS idx_file_chunks_chunk ON file_chunks(chunk_sha256);
+CREATE TABLE IF NOT EXISTS api_keys (
+  key_hash TEXT PRIMARY KEY,
+  tenant_id TEXT NOT NULL,
+  role TEXT NOT NULL,
+  disabled INTEGER NOT NULL DEFAULT 0,
+  created_at_ms INTEGER NOT NULL,
+  expires_at_ms INTEGER
+);
+CREATE TABLE IF NOT EXISTS tenant_recipients (
+  tenant_id TEXT NOT NULL,
+  recipient TEXT NOT NULL,
+  created_at_ms INTEGER NOT NULL,
+  PRIMARY KEY (tenant_id, recipient)
+);
+CREATE TABLE IF NOT EXISTS blobs (
+  sha256 TEXT PRIMARY KEY,
+  size INTEGER NOT NULL,
+  refcount INTEGER NOT NULL,
+  encrypted INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS download_tokens (
+  jti TEXT PRIMARY KEY,
+  tenant_id TEXT NOT NULL,
+  file_id TEXT NOT NULL,
+  uses_remaining INTEGER,
+  revoked INTEGER NOT NULL DEFAULT 0,
+  exp_ms INTEGER NOT NULL,
+  created_at_ms INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_download_tokens_file ON download_tokens(tenant_id, file_id);
 "#,
         )
         .map_err(|e| AppError::Db(e.to_string()))?;
@@ -293,6 +520,15 @@ CREATE INDEX IF NOT EXISTS idx_files_tenant_filename ON files(tenant_id, filenam
         let _ = conn.execute("ALTER TABLE files ADD COLUMN extract_attempt INTEGER", []);
         let _ = conn.execute("ALTER TABLE files ADD COLUMN extract_error TEXT", []);
         let _ = conn.execute("ALTER TABLE files ADD COLUMN annotations_json TEXT", []);
+        let _ = conn.execute("ALTER TABLE files ADD COLUMN chunked INTEGER NOT NULL DEFAULT 0", []);
+        let _ = conn.execute("ALTER TABLE files ADD COLUMN enc_method TEXT", []);
+        let _ = conn.execute("ALTER TABLE files ADD COLUMN verified_at_ms INTEGER", []);
+        let _ = conn.execute("ALTER TABLE files ADD COLUMN verify_status TEXT", []);
+        let _ = conn.execute("ALTER TABLE files ADD COLUMN storage_tenant TEXT", []);
+        let _ = conn.execute("ALTER TABLE files ADD COLUMN wrapped_key BLOB", []);
+        let _ = conn.execute("ALTER TABLE files ADD COLUMN key_version INTEGER", []);
+        let _ = conn.execute("ALTER TABLE blobs ADD COLUMN wrapped_key BLOB", []);
+        let _ = conn.execute("ALTER TABLE blobs ADD COLUMN key_version INTEGER", []);
         Ok(())
     })
     .await
@@ -365,20 +601,445 @@ async fn readyz(State(state): State<AppState>) -> Result<impl IntoResponse, AppE
     Ok((StatusCode::OK, Json(serde_json::json!({ "ok": true }))))
 }
 
-fn assert_can_read(auth: &AuthContext) -> Result<(), AppError> {
+/// Records a request counter and latency histogram for every route, labeled
+/// by route and status code, so handlers themselves only need to add
+/// domain-specific gauges (see `metrics_handler`) rather than instrument
+/// every call site by hand.
+async fn track_metrics(req: Request<Body>, next: Next) -> Response {
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|p| p.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+    let method = req.method().to_string();
+    let start = std::time::Instant::now();
+    let response = next.run(req).await;
+    let status = response.status().as_u16().to_string();
+
+    metrics::counter!(
+        "rustfs_http_requests_total",
+        "route" => route.clone(),
+        "method" => method.clone(),
+        "status" => status,
+    )
+    .increment(1);
+    metrics::histogram!(
+        "rustfs_http_request_duration_seconds",
+        "route" => route,
+        "method" => method,
+    )
+    .record(start.elapsed().as_secs_f64());
+
+    response
+}
+
+/// Verifies an ed25519 HTTP Signature (see `http_sig`), when a request
+/// presents one, before routing. This has to run as middleware rather than
+/// inside `auth_from_headers` because checking the signed `digest` header
+/// against the actual body requires the raw bytes, which are gone by the
+/// time a handler's `Json`/`Multipart` extractor has run; here the body is
+/// buffered once, checked, and put back so handlers see it unchanged.
+///
+/// Always strips any incoming `SIG_AUTH_TENANT_HEADER` first so a client
+/// can't simply set it themselves to impersonate a tenant.
+async fn verify_http_signature(
+    State(state): State<AppState>,
+    mut req: Request<Body>,
+    next: Next,
+) -> Result<Response, AppError> {
+    req.headers_mut().remove(SIG_AUTH_TENANT_HEADER);
+    if !req.headers().contains_key("signature") {
+        return Ok(next.run(req).await);
+    }
+
+    let method = req.method().as_str().to_string();
+    let request_target = req
+        .uri()
+        .path_and_query()
+        .map(|p| p.as_str().to_string())
+        .unwrap_or_else(|| req.uri().path().to_string());
+
+    // This runs ahead of the per-route `RequestBodyLimitLayer` (it has to,
+    // to strip/verify the tenant header before a handler's own extractors
+    // see the request), so it must not buffer an unbounded body itself just
+    // because a request happens to carry a `signature` header.
+    if let Some(declared_len) = req
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<usize>().ok())
+    {
+        if declared_len > state.max_upload_bytes {
+            return Err(AppError::PayloadTooLarge(format!(
+                "body of {declared_len} bytes exceeds the {}-byte limit",
+                state.max_upload_bytes
+            )));
+        }
+    }
+
+    let (mut parts, body) = req.into_parts();
+    let bytes = axum::body::to_bytes(body, state.max_upload_bytes).await.map_err(|_| {
+        AppError::PayloadTooLarge(format!(
+            "body exceeds the {}-byte limit",
+            state.max_upload_bytes
+        ))
+    })?;
+
+    let tenant_id = http_sig::verify(&state.tenant_pubkeys, &method, &request_target, &parts.headers, &bytes)?;
+    parts.headers.insert(
+        header::HeaderName::from_static(SIG_AUTH_TENANT_HEADER),
+        header::HeaderValue::from_str(&tenant_id)
+            .map_err(|_| AppError::InvalidRequest("invalid tenant_id".to_string()))?,
+    );
+
+    let req = Request::from_parts(parts, Body::from(bytes));
+    Ok(next.run(req).await)
+}
+
+/// Renders Prometheus text-format metrics. Total stored files and total
+/// stored bytes are gauges set here, at scrape time, rather than tracked
+/// incrementally — a fresh `COUNT`/`SUM` query is cheap and, unlike a
+/// counter threaded through every ingest/tombstone/sweep call site, can't
+/// drift out of sync with the `files` table. `rustfs_bytes_total` is the sum
+/// of live files' plaintext `size`, not bytes on disk: content shared
+/// through the chunk or blob store is counted once per file referencing it,
+/// and encryption overhead is not counted.
+async fn metrics_handler(State(state): State<AppState>) -> Result<impl IntoResponse, AppError> {
+    let (files_total, bytes_total) = with_conn(&state, move |conn| -> Result<(i64, i64), AppError> {
+        conn.query_row(
+            "SELECT COUNT(*), COALESCE(SUM(size), 0) FROM files WHERE deleted_at_ms IS NULL",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(|e| AppError::Db(e.to_string()))
+    })
+    .await?;
+
+    metrics::gauge!("rustfs_files_total").set(files_total as f64);
+    metrics::gauge!("rustfs_bytes_total").set(bytes_total as f64);
+
+    Ok((
+        StatusCode::OK,
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        state.metrics_handle.render(),
+    ))
+}
+
+fn assert_can_read(auth: &AuthContext, scope: &str) -> Result<(), AppError> {
+    match auth.role.as_str() {
+        "reader" | "writer" | "admin" => assert_scope(auth, scope),
+        _ => Err(AppError::Forbidden),
+    }
+}
+
+fn assert_can_write(auth: &AuthContext, scope: &str) -> Result<(), AppError> {
     match auth.role.as_str() {
-        "reader" | "writer" | "admin" => Ok(()),
+        "writer" | "admin" => assert_scope(auth, scope),
         _ => Err(AppError::Forbidden),
     }
 }
 
-fn assert_can_write(auth: &AuthContext) -> Result<(), AppError> {
+fn assert_is_admin(auth: &AuthContext) -> Result<(), AppError> {
     match auth.role.as_str() {
-        "writer" | "admin" => Ok(()),
+        "admin" => assert_scope(auth, "admin"),
         _ => Err(AppError::Forbidden),
     }
 }
 
+/// Narrows a role check to a specific scope for a scoped bearer token
+/// (`auth.scopes = Some(...)`); a no-op for a static API key or dev-mode
+/// auth (`auth.scopes = None`), which are only gated by role.
+fn assert_scope(auth: &AuthContext, scope: &str) -> Result<(), AppError> {
+    match &auth.scopes {
+        None => Ok(()),
+        Some(scopes) if scopes.iter().any(|s| s == scope) => Ok(()),
+        Some(_) => Err(AppError::Forbidden),
+    }
+}
+
+/// Writes any not-yet-seen chunks to the chunk store, bumps `refcount` for
+/// ones already present, and records the file's ordered chunk list.
+async fn persist_chunks(
+    state: &AppState,
+    tenant_id: &str,
+    file_id: &str,
+    chunks: Vec<Vec<u8>>,
+) -> Result<(), AppError> {
+    for (idx, data) in chunks.into_iter().enumerate() {
+        let mut hasher = Sha256::new();
+        hasher.update(&data);
+        let chunk_sha256 = hex::encode(hasher.finalize());
+        let size = data.len() as i64;
+
+        let chunk_sha256_for_db = chunk_sha256.clone();
+        let already_present = with_conn(state, move |conn| -> Result<bool, AppError> {
+            let n = conn
+                .execute(
+                    "UPDATE chunks SET refcount = refcount + 1 WHERE sha256=?1",
+                    params![chunk_sha256_for_db],
+                )
+                .map_err(|e| AppError::Db(e.to_string()))?;
+            Ok(n > 0)
+        })
+        .await?;
+
+        if !already_present {
+            let stream: storage::ByteStream =
+                Box::pin(tokio_stream::once(Ok(Bytes::from(data))));
+            state.storage.put(CHUNK_TENANT, &chunk_sha256, stream).await?;
+            let chunk_sha256_for_db = chunk_sha256.clone();
+            with_conn(state, move |conn| -> Result<(), AppError> {
+                conn.execute(
+                    "INSERT INTO chunks(sha256, size, refcount) VALUES (?1, ?2, 1)
+                     ON CONFLICT(sha256) DO UPDATE SET refcount = refcount + 1",
+                    params![chunk_sha256_for_db, size],
+                )
+                .map_err(|e| AppError::Db(e.to_string()))?;
+                Ok(())
+            })
+            .await?;
+        }
+
+        let tenant_id_for_db = tenant_id.to_string();
+        let file_id_for_db = file_id.to_string();
+        let chunk_sha256_for_db = chunk_sha256.clone();
+        with_conn(state, move |conn| -> Result<(), AppError> {
+            conn.execute(
+                "INSERT INTO file_chunks(tenant_id, file_id, idx, chunk_sha256) VALUES (?1, ?2, ?3, ?4)",
+                params![tenant_id_for_db, file_id_for_db, idx as i64, chunk_sha256_for_db],
+            )
+            .map_err(|e| AppError::Db(e.to_string()))?;
+            Ok(())
+        })
+        .await?;
+    }
+    Ok(())
+}
+
+/// What a caller needs to record on its `files` row to read a blob back:
+/// where it lives, and (since blobs are envelope-encrypted) the wrapped
+/// data key that was used to encrypt it.
+struct PersistedBlob {
+    storage_key: String,
+    wrapped_key: Vec<u8>,
+    key_version: i64,
+}
+
+/// Stores (or, if an identical blob already exists, just references) the
+/// ciphertext for a passphrase-encrypted upload under a path derived from
+/// its plaintext sha256, so uploading the same content under many tenant
+/// names costs one copy on disk instead of N. Scoped to
+/// `EncMethod::Passphrase`: every passphrase-encrypted object is decryptable
+/// with the one global master key, so reusing another tenant's ciphertext is
+/// safe. `EncMethod::Recipients` ciphertext is only decryptable by its own
+/// tenant's key and isn't eligible for this path — age's output is
+/// non-deterministic, so dedup has to key on the plaintext sha256 captured
+/// at ingest, not on a hash of the (always-unique) ciphertext.
+///
+/// `wrapped_key`/`key_version` are the data key this *upload* was encrypted
+/// with. If an identical blob already exists, its ciphertext was encrypted
+/// under whichever data key was used the first time it was stored, so that
+/// (not the caller's freshly-generated one, which is discarded along with
+/// the now-redundant tmp file) is what's returned for the caller to persist
+/// on its own `files` row.
+async fn persist_blob(
+    state: &AppState,
+    tmp: &Path,
+    sha256: &str,
+    size: i64,
+    wrapped_key: Vec<u8>,
+    key_version: i64,
+) -> Result<PersistedBlob, AppError> {
+    let key = blob_key(sha256);
+    let sha256_for_db = sha256.to_string();
+    let existing = with_conn(state, move |conn| -> Result<Option<(Vec<u8>, i64)>, AppError> {
+        let updated = conn
+            .execute(
+                "UPDATE blobs SET refcount = refcount + 1 WHERE sha256=?1",
+                params![sha256_for_db],
+            )
+            .map_err(|e| AppError::Db(e.to_string()))?;
+        if updated == 0 {
+            return Ok(None);
+        }
+        conn.query_row(
+            "SELECT wrapped_key, key_version FROM blobs WHERE sha256=?1",
+            params![sha256_for_db],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()
+        .map_err(|e| AppError::Db(e.to_string()))
+    })
+    .await?;
+
+    if let Some((existing_wrapped_key, existing_key_version)) = existing {
+        fs::remove_file(tmp).await?;
+        Ok(PersistedBlob {
+            storage_key: key,
+            wrapped_key: existing_wrapped_key,
+            key_version: existing_key_version,
+        })
+    } else {
+        let upload_file = fs::File::open(tmp).await?;
+        let upload_stream: storage::ByteStream = Box::pin(ReaderStream::new(upload_file));
+        state.storage.put(BLOB_TENANT, &key, upload_stream).await?;
+        fs::remove_file(tmp).await?;
+        let sha256_for_db = sha256.to_string();
+        let wrapped_key_for_db = wrapped_key.clone();
+        with_conn(state, move |conn| -> Result<(), AppError> {
+            conn.execute(
+                "INSERT INTO blobs(sha256, size, refcount, encrypted, wrapped_key, key_version) VALUES (?1, ?2, 1, 1, ?3, ?4)
+                 ON CONFLICT(sha256) DO UPDATE SET refcount = refcount + 1",
+                params![sha256_for_db, size, wrapped_key_for_db, key_version],
+            )
+            .map_err(|e| AppError::Db(e.to_string()))?;
+            Ok(())
+        })
+        .await?;
+        Ok(PersistedBlob {
+            storage_key: key,
+            wrapped_key,
+            key_version,
+        })
+    }
+}
+
+/// Decrements a tombstoned file's blob refcount, and physically deletes the
+/// blob once no file anywhere still references it.
+async fn release_blob(state: &AppState, sha256: &str) -> Result<(), AppError> {
+    let sha256_for_db = sha256.to_string();
+    let refcount = with_conn(state, move |conn| -> Result<i64, AppError> {
+        conn.execute(
+            "UPDATE blobs SET refcount = refcount - 1 WHERE sha256=?1",
+            params![sha256_for_db],
+        )
+        .map_err(|e| AppError::Db(e.to_string()))?;
+        let refcount: i64 = conn
+            .query_row(
+                "SELECT refcount FROM blobs WHERE sha256=?1",
+                params![sha256_for_db],
+                |row| row.get(0),
+            )
+            .map_err(|e| AppError::Db(e.to_string()))?;
+        Ok(refcount)
+    })
+    .await?;
+
+    if refcount <= 0 {
+        state.storage.delete(BLOB_TENANT, &blob_key(sha256)).await?;
+        let sha256_for_db = sha256.to_string();
+        with_conn(state, move |conn| -> Result<(), AppError> {
+            conn.execute("DELETE FROM blobs WHERE sha256=?1", params![sha256_for_db])
+                .map_err(|e| AppError::Db(e.to_string()))?;
+            Ok(())
+        })
+        .await?;
+    }
+    Ok(())
+}
+
+/// Streams a chunked file back by fetching its chunks from the chunk store
+/// in order, following the same "spawn a blocking/async producer, forward
+/// through an mpsc channel" shape the encrypted download path already uses.
+async fn reassemble_chunks(
+    state: &AppState,
+    tenant_id: &str,
+    file_id: &str,
+) -> Result<storage::ByteStream, AppError> {
+    let tenant_id_db = tenant_id.to_string();
+    let file_id_db = file_id.to_string();
+    let chunk_hashes = with_conn(state, move |conn| -> Result<Vec<String>, AppError> {
+        let mut stmt = conn
+            .prepare("SELECT chunk_sha256 FROM file_chunks WHERE tenant_id=?1 AND file_id=?2 ORDER BY idx ASC")
+            .map_err(|e| AppError::Db(e.to_string()))?;
+        let mut rows = stmt
+            .query(params![tenant_id_db, file_id_db])
+            .map_err(|e| AppError::Db(e.to_string()))?;
+        let mut out = Vec::new();
+        while let Some(row) = rows.next().map_err(|e| AppError::Db(e.to_string()))? {
+            out.push(row.get(0).map_err(|e| AppError::Db(e.to_string()))?);
+        }
+        Ok(out)
+    })
+    .await?;
+
+    let storage = state.storage.clone();
+    let (tx, rx) = tokio::sync::mpsc::channel::<Result<Bytes, std::io::Error>>(8);
+    tokio::spawn(async move {
+        use tokio_stream::StreamExt;
+        for chunk_sha256 in chunk_hashes {
+            let mut stream = match storage.get(CHUNK_TENANT, &chunk_sha256).await {
+                Ok(s) => s,
+                Err(e) => {
+                    let _ = tx
+                        .send(Err(std::io::Error::new(std::io::ErrorKind::Other, e.to_string())))
+                        .await;
+                    return;
+                }
+            };
+            while let Some(piece) = stream.next().await {
+                if tx.send(piece).await.is_err() {
+                    return;
+                }
+            }
+        }
+    });
+    Ok(Box::pin(ReceiverStream::new(rx)))
+}
+
+/// Decrements the refcount of every chunk a (now-tombstoned) file referenced,
+/// and deletes from the chunk store any whose refcount reaches zero.
+async fn release_chunks(state: &AppState, tenant_id: &str, file_id: &str) -> Result<(), AppError> {
+    let tenant_id_db = tenant_id.to_string();
+    let file_id_db = file_id.to_string();
+    let chunk_hashes = with_conn(state, move |conn| -> Result<Vec<String>, AppError> {
+        let mut stmt = conn
+            .prepare("SELECT chunk_sha256 FROM file_chunks WHERE tenant_id=?1 AND file_id=?2")
+            .map_err(|e| AppError::Db(e.to_string()))?;
+        let mut rows = stmt
+            .query(params![tenant_id_db, file_id_db])
+            .map_err(|e| AppError::Db(e.to_string()))?;
+        let mut out = Vec::new();
+        while let Some(row) = rows.next().map_err(|e| AppError::Db(e.to_string()))? {
+            out.push(row.get(0).map_err(|e| AppError::Db(e.to_string()))?);
+        }
+        Ok(out)
+    })
+    .await?;
+
+    for chunk_sha256 in chunk_hashes {
+        let chunk_sha256_for_db = chunk_sha256.clone();
+        let refcount = with_conn(state, move |conn| -> Result<i64, AppError> {
+            conn.execute(
+                "UPDATE chunks SET refcount = refcount - 1 WHERE sha256=?1",
+                params![chunk_sha256_for_db],
+            )
+            .map_err(|e| AppError::Db(e.to_string()))?;
+            let refcount: i64 = conn
+                .query_row(
+                    "SELECT refcount FROM chunks WHERE sha256=?1",
+                    params![chunk_sha256_for_db],
+                    |row| row.get(0),
+                )
+                .map_err(|e| AppError::Db(e.to_string()))?;
+            Ok(refcount)
+        })
+        .await?;
+
+        if refcount <= 0 {
+            state.storage.delete(CHUNK_TENANT, &chunk_sha256).await?;
+            let chunk_sha256_for_db = chunk_sha256.clone();
+            with_conn(state, move |conn| -> Result<(), AppError> {
+                conn.execute("DELETE FROM chunks WHERE sha256=?1", params![chunk_sha256_for_db])
+                    .map_err(|e| AppError::Db(e.to_string()))?;
+                Ok(())
+            })
+            .await?;
+        }
+    }
+    Ok(())
+}
+
 async fn ingest(
     State(state): State<AppState>,
     headers: HeaderMap,
@@ -392,6 +1053,14 @@ async fn ingest(
     let mut tmp_path: Option<PathBuf> = None;
     let mut sha = Sha256::new();
     let mut size: i64 = 0;
+    let mut chunker = chunker::Chunker::new();
+    let mut chunks: Vec<Vec<u8>> = Vec::new();
+    let mut auth: Option<AuthContext> = None;
+    let mut encrypted = false;
+    let mut chunked = false;
+    let mut enc_method_for_db: Option<&'static str> = None;
+    let mut wrapped_key_for_db: Option<Vec<u8>> = None;
+    let mut key_version_for_db: Option<i64> = None;
 
     fs::create_dir_all(state.data_dir.join("tmp")).await?;
 
@@ -437,23 +1106,112 @@ async fn ingest(
                 filename = field.file_name().map(|s| s.to_string());
                 mime = field.content_type().map(|m| m.to_string());
 
-                let tmp = state
-                    .data_dir
-                    .join("tmp")
-                    .join(format!("upload-{}.bin", uuid::Uuid::new_v4()));
-                let mut out = fs::File::create(&tmp).await?;
+                // Auth must be resolved before the body is read, since
+                // whether to encrypt (and for which recipients) depends on
+                // the tenant. In `RUSTFS_REQUIRE_API_KEY` mode the tenant
+                // comes from `x-api-key`, resolvable immediately; in the
+                // no-auth dev mode it comes from the `tenant_id` field, so
+                // that field must be sent before `file` in the multipart body.
+                if auth.is_none() {
+                    auth = Some(auth_from_headers(&state, &headers, tenant_hint.as_deref()).await?);
+                }
+                let auth_ref = auth.as_ref().expect("just resolved above");
+                assert_can_write(auth_ref, "ingest")?;
+                let tenant_id = auth_ref.tenant_id.clone();
+                let recipients = crypto::tenant_recipients(&state, &tenant_id).await?;
+                encrypted = state.master_keys.is_some() || !recipients.is_empty();
+                chunked = !encrypted;
+
                 let mut stream = field;
-                while let Some(chunk) = stream
-                    .chunk()
-                    .await
-                    .map_err(|e| AppError::InvalidRequest(e.to_string()))?
-                {
-                    sha.update(&chunk);
-                    size += chunk.len() as i64;
-                    out.write_all(&chunk).await?;
+                if chunked {
+                    // Plaintext uploads go through the content-defined chunk
+                    // store for block-level dedup, so the plaintext tmp file
+                    // below is expected (it's exactly what ends up in the
+                    // object store).
+                    let tmp = state
+                        .data_dir
+                        .join("tmp")
+                        .join(format!("upload-{}.bin", uuid::Uuid::new_v4()));
+                    let mut out = fs::File::create(&tmp).await?;
+                    while let Some(chunk) = stream
+                        .chunk()
+                        .await
+                        .map_err(|e| AppError::InvalidRequest(e.to_string()))?
+                    {
+                        sha.update(&chunk);
+                        size += chunk.len() as i64;
+                        chunks.extend(chunker.push(&chunk));
+                        out.write_all(&chunk).await?;
+                    }
+                    if let Some(tail) = chunker.finish() {
+                        chunks.push(tail);
+                    }
+                    out.flush().await?;
+                    tmp_path = Some(tmp);
+                } else {
+                    // Encrypt in the same pass the body is read: chunks go
+                    // straight through the age writer into the final `.age`
+                    // tmp file on a blocking task, fed over a channel, so
+                    // plaintext is never written to disk (not even
+                    // transiently) and there's no second read-and-rewrite
+                    // pass. This means a dedup hit (detected only once the
+                    // whole body is hashed, below) still pays for the
+                    // encryption; that's the tradeoff for not buffering the
+                    // body to learn its hash before encrypting it.
+                    // Passphrase-mode uploads are envelope-encrypted: a fresh
+                    // random data key encrypts the blob below, and only that
+                    // (tiny) data key is wrapped under the current master
+                    // key and stored on the file row. Rotating the master
+                    // key (`rotate_key`) then only means rewrapping these
+                    // bytes per file, never re-encrypting blob ciphertext.
+                    let (encryptor, enc_method) = if recipients.is_empty() {
+                        let ring = state.master_keys.as_ref().ok_or_else(|| {
+                            AppError::Crypto("missing master key".to_string())
+                        })?;
+                        let (version, master_key) = {
+                            let guard = ring.read().await;
+                            (guard.current_version, guard.current.clone())
+                        };
+                        let data_key = crypto::generate_data_key();
+                        wrapped_key_for_db = Some(crypto::wrap_data_key(&data_key, master_key)?);
+                        key_version_for_db = Some(version);
+                        crypto::build_encryptor(&[], Some(data_key))?
+                    } else {
+                        crypto::build_encryptor(&recipients, None)?
+                    };
+                    enc_method_for_db = Some(enc_method.as_db_str());
+                    let tmp = state
+                        .data_dir
+                        .join("tmp")
+                        .join(format!("upload-{}.age", uuid::Uuid::new_v4()));
+                    let out_path = tmp.clone();
+                    let (tx, mut rx) = tokio::sync::mpsc::channel::<Bytes>(8);
+                    let write_task = tokio::task::spawn_blocking(move || -> Result<(), AppError> {
+                        let output = std::fs::File::create(&out_path)?;
+                        let mut writer = encryptor
+                            .wrap_output(output)
+                            .map_err(|e| AppError::Crypto(e.to_string()))?;
+                        while let Some(chunk) = rx.blocking_recv() {
+                            writer.write_all(&chunk)?;
+                        }
+                        writer.finish().map_err(|e| AppError::Crypto(e.to_string()))?;
+                        Ok(())
+                    });
+                    while let Some(chunk) = stream
+                        .chunk()
+                        .await
+                        .map_err(|e| AppError::InvalidRequest(e.to_string()))?
+                    {
+                        sha.update(&chunk);
+                        size += chunk.len() as i64;
+                        tx.send(chunk)
+                            .await
+                            .map_err(|_| AppError::Crypto("encryption task ended early".to_string()))?;
+                    }
+                    drop(tx);
+                    write_task.await.map_err(|e| AppError::Crypto(e.to_string()))??;
+                    tmp_path = Some(tmp);
                 }
-                out.flush().await?;
-                tmp_path = Some(tmp);
             }
             _ => {
                 // ignore unknown fields
@@ -462,24 +1220,22 @@ async fn ingest(
     }
 
     let request_id = headers.get("x-request-id").and_then(|v| v.to_str().ok());
-    let auth = auth_from_headers(&state, &headers, tenant_hint.as_deref())?;
-    assert_can_write(&auth)?;
+    let auth = match auth {
+        Some(a) => a,
+        None => auth_from_headers(&state, &headers, tenant_hint.as_deref()).await?,
+    };
+    assert_can_write(&auth, "ingest")?;
     let tenant_id = auth.tenant_id.clone();
     let tmp = tmp_path.ok_or_else(|| AppError::InvalidRequest("missing multipart field: file".to_string()))?;
     let filename = filename.unwrap_or_else(|| "file".to_string());
     let sha256 = hex::encode(sha.finalize());
     let file_id = sha256.clone();
 
-    let tenant_dir = state.data_dir.join("objects").join(&tenant_id);
-    fs::create_dir_all(&tenant_dir).await?;
-
     let created_at_ms = now_ms();
-    let encrypted = state.master_key.is_some();
-    let final_path_plain = tenant_dir.join(&file_id);
-    let final_path = if encrypted {
-        tenant_dir.join(format!("{file_id}.age"))
+    let storage_key = if encrypted {
+        format!("{file_id}.age")
     } else {
-        final_path_plain.clone()
+        file_id.clone()
     };
 
     // Insert-or-return existing by (tenant_id, file_id)
@@ -535,40 +1291,51 @@ async fn ingest(
         ));
     }
 
-    // Move to final location, encrypt if configured.
-    if encrypted {
-        // Write plaintext to deterministic path first (temp name), then encrypt to .age and delete plaintext.
-        fs::rename(&tmp, &final_path_plain).await?;
-
-        let in_path = final_path_plain.clone();
-        let out_path = final_path.clone();
-        let key = state.master_key.clone().ok_or_else(|| AppError::Crypto("missing master key".to_string()))?;
-        tokio::task::spawn_blocking(move || -> Result<(), AppError> {
-            let input = std::fs::File::open(&in_path)?;
-            let output = std::fs::File::create(&out_path)?;
-            let encryptor = age::Encryptor::with_user_passphrase(key);
-            let mut writer = encryptor
-                .wrap_output(output)
-                .map_err(|e| AppError::Crypto(e.to_string()))?;
-            let mut reader = std::io::BufReader::new(input);
-            std::io::copy(&mut reader, &mut writer)?;
-            writer.finish().map_err(|e| AppError::Crypto(e.to_string()))?;
-            Ok(())
-        })
-        .await
-        .map_err(|e| AppError::Crypto(e.to_string()))??;
-
-        // Remove plaintext
-        fs::remove_file(&final_path_plain).await?;
+    // Hand the staged upload to the configured storage backend. Plaintext
+    // uploads go through the content-defined chunk store for block-level
+    // dedup; passphrase-encrypted uploads go through the content-addressed
+    // blob store, since every tenant shares the one master key and so can
+    // safely reuse each other's ciphertext for identical plaintext;
+    // recipient-encrypted uploads were already written straight to their
+    // own tenant-scoped `.age` tmp file above, so this just ships it as-is.
+    let is_blob = enc_method_for_db.as_deref() == Some(crypto::EncMethod::Passphrase.as_db_str());
+    let storage_tenant: Option<String> = if chunked {
+        None
+    } else if is_blob {
+        Some(BLOB_TENANT.to_string())
     } else {
-        fs::rename(&tmp, &final_path).await?;
-    }
-
-    let storage_path = final_path
-        .strip_prefix(&state.data_dir)
-        .unwrap_or(&final_path)
-        .to_string_lossy()
-        .to_string();
+        None
+    };
+    let storage_path = if chunked {
+        persist_chunks(&state, &tenant_id, &file_id, chunks).await?;
+        fs::remove_file(&tmp).await?;
+        String::new()
+    } else if is_blob {
+        let persisted = persist_blob(
+            &state,
+            &tmp,
+            &sha256,
+            size,
+            wrapped_key_for_db
+                .clone()
+                .ok_or_else(|| AppError::Crypto("missing data key".to_string()))?,
+            key_version_for_db.ok_or_else(|| AppError::Crypto("missing key version".to_string()))?,
+        )
+        .await?;
+        // A dedup hit reuses an existing blob's ciphertext, which was
+        // encrypted under whatever data key was wrapped the first time that
+        // blob was stored — not the one just generated above for this
+        // upload — so this file's row must record that one instead.
+        wrapped_key_for_db = Some(persisted.wrapped_key);
+        key_version_for_db = Some(persisted.key_version);
+        persisted.storage_key
+    } else {
+        let upload_file = fs::File::open(&tmp).await?;
+        let upload_stream: storage::ByteStream = Box::pin(ReaderStream::new(upload_file));
+        state.storage.put(&tenant_id, &storage_key, upload_stream).await?;
+        fs::remove_file(&tmp).await?;
+        storage_key.clone()
+    };
 
     let tenant_id_for_db = tenant_id.clone();
     let session_id_for_db = session_id.clone();
@@ -578,13 +1345,15 @@ async fn ingest(
     let sha256_for_db = sha256.clone();
     let file_id_for_db = file_id.clone();
     let encrypted_i = if encrypted { 1 } else { 0 };
+    let chunked_i = if chunked { 1 } else { 0 };
+    let enc_method_for_db = enc_method_for_db.map(|s| s.to_string());
     let extract_status_for_db = "pending".to_string();
     let extract_updated_at_for_db = now_ms();
     let extract_attempt_for_db = 0i64;
     with_conn(&state, move |conn| {
         conn.execute(
-            "INSERT INTO files(file_id, tenant_id, session_id, filename, mime, size, sha256, created_at_ms, source, encrypted, storage_path, extract_status, extract_updated_at_ms, extract_attempt)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+            "INSERT INTO files(file_id, tenant_id, session_id, filename, mime, size, sha256, created_at_ms, source, encrypted, storage_path, chunked, enc_method, extract_status, extract_updated_at_ms, extract_attempt, storage_tenant, wrapped_key, key_version)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19)",
             params![
                 file_id_for_db,
                 tenant_id_for_db,
@@ -597,9 +1366,14 @@ async fn ingest(
                 source_for_db,
                 encrypted_i,
                 storage_path,
+                chunked_i,
+                enc_method_for_db,
                 extract_status_for_db,
                 extract_updated_at_for_db,
                 extract_attempt_for_db,
+                storage_tenant,
+                wrapped_key_for_db,
+                key_version_for_db,
             ],
         )
         .map_err(|e| AppError::Db(e.to_string()))?;
@@ -639,8 +1413,8 @@ async fn search(
     headers: HeaderMap,
     Query(q): Query<SearchQuery>,
 ) -> Result<impl IntoResponse, AppError> {
-    let auth = auth_from_headers(&state, &headers, q.tenant_id.as_deref())?;
-    assert_can_read(&auth)?;
+    let auth = auth_from_headers(&state, &headers, q.tenant_id.as_deref()).await?;
+    assert_can_read(&auth, "search")?;
     let tenant_id = auth.tenant_id;
     let limit = q.limit.unwrap_or(50).clamp(1, 200) as i64;
     let session_id = q.session_id.clone().filter(|s| !s.trim().is_empty());
@@ -765,8 +1539,8 @@ async fn meta(
     headers: HeaderMap,
     axum::extract::Path(path): axum::extract::Path<PathParams>,
 ) -> Result<impl IntoResponse, AppError> {
-    let auth = auth_from_headers(&state, &headers, None)?;
-    assert_can_read(&auth)?;
+    let auth = auth_from_headers(&state, &headers, None).await?;
+    assert_can_read(&auth, "search")?;
     let tenant_id = auth.tenant_id;
     let file_id = path.file_id.trim().to_string();
     if file_id.is_empty() {
@@ -823,8 +1597,8 @@ async fn pending_extract(
     headers: HeaderMap,
     Query(q): Query<PendingQuery>,
 ) -> Result<impl IntoResponse, AppError> {
-    let auth = auth_from_headers(&state, &headers, q.tenant_id.as_deref())?;
-    assert_can_read(&auth)?;
+    let auth = auth_from_headers(&state, &headers, q.tenant_id.as_deref()).await?;
+    assert_can_read(&auth, "search")?;
     let tenant_id = auth.tenant_id;
     let limit = q.limit.unwrap_or(25).clamp(1, 200) as i64;
 
@@ -883,8 +1657,8 @@ async fn upsert_annotations(
     Json(req): Json<AnnotationsRequest>,
 ) -> Result<impl IntoResponse, AppError> {
     let request_id = headers.get("x-request-id").and_then(|v| v.to_str().ok());
-    let auth = auth_from_headers(&state, &headers, None)?;
-    assert_can_write(&auth)?;
+    let auth = auth_from_headers(&state, &headers, None).await?;
+    assert_can_write(&auth, "ingest")?;
 
     let file_id = path.file_id.trim().to_string();
     if file_id.is_empty() {
@@ -943,8 +1717,8 @@ async fn set_extract_status(
     Json(req): Json<ExtractStatusRequest>,
 ) -> Result<impl IntoResponse, AppError> {
     let request_id = headers.get("x-request-id").and_then(|v| v.to_str().ok());
-    let auth = auth_from_headers(&state, &headers, None)?;
-    assert_can_write(&auth)?;
+    let auth = auth_from_headers(&state, &headers, None).await?;
+    assert_can_write(&auth, "ingest")?;
 
     let file_id = path.file_id.trim().to_string();
     if file_id.is_empty() {
@@ -1005,13 +1779,151 @@ async fn set_extract_status(
     ))
 }
 
+/// Parses a single-range `Range: bytes=...` request header against `total`
+/// (the plaintext size). Returns `Ok(None)` when there's no range header, or
+/// it's a multi-range request (a comma in the spec) — both are served as a
+/// plain `200` with the whole body. Returns `Ok(Some((start, end)))`
+/// (inclusive) for a satisfiable single range, covering suffix (`-N`) and
+/// open-ended (`N-`) forms. Returns `Err(())` for an unsatisfiable range;
+/// the caller responds `416` with `Content-Range: bytes */<total>`.
+fn parse_range(raw: Option<&str>, total: u64) -> Result<Option<(u64, u64)>, ()> {
+    let raw = match raw {
+        Some(r) => r,
+        None => return Ok(None),
+    };
+    let spec = match raw.strip_prefix("bytes=") {
+        Some(s) => s,
+        None => return Ok(None),
+    };
+    if spec.contains(',') {
+        return Ok(None);
+    }
+    let (start_s, end_s) = spec.split_once('-').ok_or(())?;
+    if total == 0 {
+        return Err(());
+    }
+    let (start, end) = if start_s.is_empty() {
+        let suffix_len: u64 = end_s.parse().map_err(|_| ())?;
+        if suffix_len == 0 {
+            return Err(());
+        }
+        let suffix_len = suffix_len.min(total);
+        (total - suffix_len, total - 1)
+    } else {
+        let start: u64 = start_s.parse().map_err(|_| ())?;
+        let end: u64 = if end_s.is_empty() {
+            total - 1
+        } else {
+            end_s.parse().map_err(|_| ())?
+        };
+        (start, end.min(total - 1))
+    };
+    if start > end || start >= total {
+        return Err(());
+    }
+    Ok(Some((start, end)))
+}
+
+/// Wraps a full-file (non-range) download stream so every chunk is fed
+/// through a `Sha256` hasher as it passes to the client. Once the source
+/// stream ends, the digest is compared against `expected_sha256`: a match is
+/// silent, a mismatch records an `integrity_mismatch` audit entry and ends
+/// the body with an I/O error so the client sees a failed transfer rather
+/// than silently-corrupt bytes (any chunks already sent can't be recalled,
+/// which is the tradeoff of verifying while streaming instead of before).
+fn verify_stream(
+    mut stream: storage::ByteStream,
+    expected_sha256: String,
+    state: AppState,
+    tenant_id: String,
+    file_id: String,
+) -> storage::ByteStream {
+    use tokio_stream::StreamExt;
+    let (tx, rx) = tokio::sync::mpsc::channel::<std::io::Result<Bytes>>(8);
+    tokio::spawn(async move {
+        let mut hasher = Sha256::new();
+        loop {
+            match stream.next().await {
+                Some(Ok(chunk)) => {
+                    hasher.update(&chunk);
+                    if tx.send(Ok(chunk)).await.is_err() {
+                        return;
+                    }
+                }
+                Some(Err(e)) => {
+                    let _ = tx.send(Err(e)).await;
+                    return;
+                }
+                None => break,
+            }
+        }
+        let actual_sha256 = hex::encode(hasher.finalize());
+        if actual_sha256 != expected_sha256 {
+            append_audit(
+                &state,
+                AuditEntry {
+                    id: uuid::Uuid::new_v4().to_string(),
+                    ts_ms: now_ms(),
+                    action: "integrity_mismatch",
+                    tenant_id: &tenant_id,
+                    key_id: None,
+                    request_id: None,
+                    file_id: Some(&file_id),
+                    extra: serde_json::json!({ "expected_sha256": expected_sha256, "actual_sha256": actual_sha256 }),
+                },
+            )
+            .await;
+            let _ = tx
+                .send(Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    "integrity check failed",
+                )))
+                .await;
+        }
+    });
+    Box::pin(ReceiverStream::new(rx))
+}
+
+/// Resolves the effective passphrase to hand `crypto::resolve_identity` for
+/// a given file: for `EncMethod::Recipients` there's nothing to unwrap, so
+/// `None`. For `EncMethod::Passphrase`, unwraps the file's own data key
+/// using the master key at its `key_version` (current or retired); a row
+/// with no `wrapped_key` predates envelope encryption and was encrypted
+/// directly under the then-current master key, so it falls back to that.
+async fn resolve_passphrase_key(
+    state: &AppState,
+    enc_method: &crypto::EncMethod,
+    wrapped_key: Option<Vec<u8>>,
+    key_version: Option<i64>,
+) -> Result<Option<SecretString>, AppError> {
+    if matches!(enc_method, crypto::EncMethod::Recipients) {
+        return Ok(None);
+    }
+    let ring = state
+        .master_keys
+        .as_ref()
+        .ok_or_else(|| AppError::Crypto("encrypted file but no master key configured".to_string()))?;
+    match (wrapped_key, key_version) {
+        (Some(wrapped), Some(version)) => {
+            let master_key = {
+                let guard = ring.read().await;
+                guard.key_for_version(version).ok_or_else(|| {
+                    AppError::Crypto(format!("master key version {version} is no longer available"))
+                })?
+            };
+            Ok(Some(crypto::unwrap_data_key(&wrapped, master_key)?))
+        }
+        _ => Ok(Some(ring.read().await.current.clone())),
+    }
+}
+
 async fn download(
     State(state): State<AppState>,
     headers: HeaderMap,
     axum::extract::Path(path): axum::extract::Path<PathParams>,
 ) -> Result<impl IntoResponse, AppError> {
-    let auth = auth_from_headers(&state, &headers, None)?;
-    assert_can_read(&auth)?;
+    let auth = auth_from_headers(&state, &headers, None).await?;
+    assert_can_read(&auth, "download")?;
     let tenant_id = auth.tenant_id;
     let file_id = path.file_id.trim().to_string();
     if file_id.is_empty() {
@@ -1023,26 +1935,42 @@ async fn download(
         filename: String,
         mime: Option<String>,
         encrypted: bool,
+        chunked: bool,
         storage_path: String,
+        storage_tenant: Option<String>,
+        enc_method: Option<String>,
+        size: i64,
+        sha256: String,
+        wrapped_key: Option<Vec<u8>>,
+        key_version: Option<i64>,
     }
 
     let tenant_id_db = tenant_id.clone();
+    let file_id_db = file_id.clone();
     let row = with_conn(&state, move |conn| -> Result<Option<Row>, AppError> {
         let mut stmt = conn
             .prepare(
-                "SELECT filename, mime, encrypted, storage_path FROM files WHERE tenant_id=?1 AND file_id=?2 AND deleted_at_ms IS NULL",
+                "SELECT filename, mime, encrypted, storage_path, chunked, enc_method, size, sha256, storage_tenant, wrapped_key, key_version FROM files WHERE tenant_id=?1 AND file_id=?2 AND deleted_at_ms IS NULL",
             )
             .map_err(|e| AppError::Db(e.to_string()))?;
         let mut rows = stmt
-            .query(params![tenant_id_db, file_id])
+            .query(params![tenant_id_db, file_id_db])
             .map_err(|e| AppError::Db(e.to_string()))?;
         if let Some(row) = rows.next().map_err(|e| AppError::Db(e.to_string()))? {
             let encrypted_i: i64 = row.get(2).map_err(|e| AppError::Db(e.to_string()))?;
+            let chunked_i: i64 = row.get(4).unwrap_or(0);
             return Ok(Some(Row {
                 filename: row.get(0).map_err(|e| AppError::Db(e.to_string()))?,
                 mime: row.get(1).map_err(|e| AppError::Db(e.to_string()))?,
                 encrypted: encrypted_i != 0,
+                chunked: chunked_i != 0,
                 storage_path: row.get(3).map_err(|e| AppError::Db(e.to_string()))?,
+                enc_method: row.get(5).unwrap_or(None),
+                size: row.get(6).map_err(|e| AppError::Db(e.to_string()))?,
+                sha256: row.get(7).map_err(|e| AppError::Db(e.to_string()))?,
+                storage_tenant: row.get(8).unwrap_or(None),
+                wrapped_key: row.get(9).unwrap_or(None),
+                key_version: row.get(10).unwrap_or(None),
             }));
         }
         Ok(None)
@@ -1050,12 +1978,30 @@ async fn download(
     .await?
     .ok_or(AppError::NotFound)?;
 
-    let abs = state.data_dir.join(row.storage_path.trim_start_matches('/'));
-    if !abs.exists() {
+    let storage_tenant_id = row.storage_tenant.clone().unwrap_or_else(|| tenant_id.clone());
+
+    if !row.chunked && !state.storage.exists(&storage_tenant_id, &row.storage_path).await? {
         return Err(AppError::NotFound);
     }
 
+    let total_size = row.size.max(0) as u64;
+    let range = match parse_range(
+        headers.get(header::RANGE).and_then(|v| v.to_str().ok()),
+        total_size,
+    ) {
+        Ok(r) => r,
+        Err(()) => {
+            let mut headers_out = HeaderMap::new();
+            headers_out.insert(
+                header::CONTENT_RANGE,
+                format!("bytes */{total_size}").parse().unwrap(),
+            );
+            return Ok((StatusCode::RANGE_NOT_SATISFIABLE, headers_out, Body::empty()));
+        }
+    };
+
     let mut headers_out = HeaderMap::new();
+    headers_out.insert(header::ACCEPT_RANGES, "bytes".parse().unwrap());
     headers_out.insert(
         header::CONTENT_DISPOSITION,
         format!("attachment; filename=\"{}\"", row.filename.replace('"', "_"))
@@ -1067,48 +2013,207 @@ async fn download(
             headers_out.insert(header::CONTENT_TYPE, v);
         }
     }
+    let status = if let Some((start, end)) = range {
+        headers_out.insert(
+            header::CONTENT_RANGE,
+            format!("bytes {start}-{end}/{total_size}").parse().unwrap(),
+        );
+        headers_out.insert(header::CONTENT_LENGTH, (end - start + 1).to_string().parse().unwrap());
+        StatusCode::PARTIAL_CONTENT
+    } else {
+        StatusCode::OK
+    };
+
+    if range.is_none() {
+        if let Some(cached) = state.download_cache.get(&file_id) {
+            headers_out.insert(header::CONTENT_LENGTH, cached.bytes.len().to_string().parse().unwrap());
+            return Ok((status, headers_out, Body::from(cached.bytes)));
+        }
+    }
+    let cache_this_download = range.is_none() && state.download_cache.is_enabled();
+
+    // Integrity verification streams the whole file against its stored
+    // digest; a range request only ever sees a slice, which can never equal
+    // the full-file sha256, so it's skipped for ranged requests.
+    if row.chunked {
+        let stream = reassemble_chunks(&state, &tenant_id, &file_id).await?;
+        if cache_this_download {
+            let stream = verify_stream(stream, row.sha256.clone(), state.clone(), tenant_id.clone(), file_id.clone());
+            let bytes = collect_stream_bytes(stream).await?;
+            state.download_cache.insert(
+                file_id.clone(),
+                cache::CachedDownload { bytes: bytes.clone() },
+            );
+            headers_out.insert(header::CONTENT_LENGTH, bytes.len().to_string().parse().unwrap());
+            return Ok((status, headers_out, Body::from(bytes)));
+        }
+        let stream = match range {
+            Some((start, end)) => storage::bound_stream(stream, start, end - start + 1),
+            None => verify_stream(stream, row.sha256.clone(), state.clone(), tenant_id.clone(), file_id.clone()),
+        };
+        return Ok((status, headers_out, Body::from_stream(stream)));
+    }
 
     if !row.encrypted {
-        let file = fs::File::open(abs).await?;
-        let body = Body::from_stream(ReaderStream::new(file));
-        return Ok((StatusCode::OK, headers_out, body));
+        if cache_this_download {
+            let stream = state.storage.get(&storage_tenant_id, &row.storage_path).await?;
+            let stream = verify_stream(stream, row.sha256.clone(), state.clone(), tenant_id.clone(), file_id.clone());
+            let bytes = collect_stream_bytes(stream).await?;
+            state.download_cache.insert(
+                file_id.clone(),
+                cache::CachedDownload { bytes: bytes.clone() },
+            );
+            headers_out.insert(header::CONTENT_LENGTH, bytes.len().to_string().parse().unwrap());
+            return Ok((status, headers_out, Body::from(bytes)));
+        }
+        let stream = match range {
+            Some((start, end)) => {
+                state
+                    .storage
+                    .get_range(&storage_tenant_id, &row.storage_path, start, end - start + 1)
+                    .await?
+            }
+            None => {
+                let stream = state.storage.get(&storage_tenant_id, &row.storage_path).await?;
+                verify_stream(stream, row.sha256.clone(), state.clone(), tenant_id.clone(), file_id.clone())
+            }
+        };
+        return Ok((status, headers_out, Body::from_stream(stream)));
     }
 
     // Encrypted: decrypt on the fly (blocking reader -> async body stream).
-    let key = state
-        .master_key
-        .clone()
-        .ok_or_else(|| AppError::Crypto("encrypted file but no master key configured".to_string()))?;
+    // age isn't seekable, so a range request still decrypts from the start
+    // and discards up to `start` bytes of plaintext before streaming.
+    let enc_method = crypto::EncMethod::from_db_str(row.enc_method.as_deref());
+    let passphrase_key =
+        resolve_passphrase_key(&state, &enc_method, row.wrapped_key.clone(), row.key_version).await?;
+    let identity = crypto::resolve_identity(
+        &enc_method,
+        &tenant_id,
+        passphrase_key,
+        &state.tenant_identities,
+        headers.get("x-age-identity").and_then(|v| v.to_str().ok()),
+    )?;
+    let ciphertext = read_storage_to_vec(&state, &storage_tenant_id, &row.storage_path).await?;
+    let (skip, mut take) = match range {
+        Some((start, end)) => (start, end - start + 1),
+        None => (0, total_size),
+    };
+    let verify_expected = if range.is_none() { Some(row.sha256.clone()) } else { None };
     let (tx, rx) = tokio::sync::mpsc::channel::<Result<Bytes, std::io::Error>>(8);
+    let state_for_audit = state.clone();
+    let tenant_id_for_audit = tenant_id.clone();
+    let file_id_for_audit = file_id.clone();
     tokio::task::spawn_blocking(move || {
-        let result: Result<(), std::io::Error> = (|| {
-            let input = std::fs::File::open(abs)?;
-            let decryptor = age::Decryptor::new(std::io::BufReader::new(input))
+        let result: Result<Option<String>, std::io::Error> = (|| {
+            let decryptor = age::Decryptor::new(std::io::Cursor::new(ciphertext))
                 .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))?;
-            let identity = age::scrypt::Identity::new(key);
             let mut reader = decryptor
-                .decrypt(iter::once(&identity as &dyn age::Identity))
+                .decrypt(iter::once(identity.as_ref()))
                 .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))?;
 
             let mut buf = vec![0u8; 64 * 1024];
-            loop {
-                let n = reader.read(&mut buf)?;
+            let mut skip = skip;
+            while skip > 0 {
+                let want = skip.min(buf.len() as u64) as usize;
+                let n = reader.read(&mut buf[..want])?;
+                if n == 0 {
+                    break;
+                }
+                skip -= n as u64;
+            }
+            let mut hasher = verify_expected.as_ref().map(|_| Sha256::new());
+            while take > 0 {
+                let want = take.min(buf.len() as u64) as usize;
+                let n = reader.read(&mut buf[..want])?;
                 if n == 0 {
                     break;
                 }
+                take -= n as u64;
+                if let Some(hasher) = hasher.as_mut() {
+                    hasher.update(&buf[..n]);
+                }
                 if tx.blocking_send(Ok(Bytes::copy_from_slice(&buf[..n]))).is_err() {
                     break;
                 }
             }
-            Ok(())
+            if let (Some(expected), Some(hasher)) = (verify_expected, hasher) {
+                let actual = hex::encode(hasher.finalize());
+                if actual != expected {
+                    return Ok(Some(actual));
+                }
+            }
+            Ok(None)
         })();
-        if let Err(e) = result {
-            let _ = tx.blocking_send(Err(e));
+        match result {
+            Ok(Some(actual_sha256)) => {
+                let handle = tokio::runtime::Handle::current();
+                handle.block_on(append_audit(
+                    &state_for_audit,
+                    AuditEntry {
+                        id: uuid::Uuid::new_v4().to_string(),
+                        ts_ms: now_ms(),
+                        action: "integrity_mismatch",
+                        tenant_id: &tenant_id_for_audit,
+                        key_id: None,
+                        request_id: None,
+                        file_id: Some(&file_id_for_audit),
+                        extra: serde_json::json!({ "actual_sha256": actual_sha256 }),
+                    },
+                ));
+                let _ = tx.blocking_send(Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    "integrity check failed",
+                )));
+            }
+            Ok(None) => {}
+            Err(e) => {
+                let _ = tx.blocking_send(Err(e));
+            }
         }
     });
 
+    if cache_this_download {
+        let bytes = collect_stream_bytes(ReceiverStream::new(rx)).await?;
+        state.download_cache.insert(
+            file_id.clone(),
+            cache::CachedDownload { bytes: bytes.clone() },
+        );
+        headers_out.insert(header::CONTENT_LENGTH, bytes.len().to_string().parse().unwrap());
+        return Ok((status, headers_out, Body::from(bytes)));
+    }
     let body = Body::from_stream(ReceiverStream::new(rx));
-    Ok((StatusCode::OK, headers_out, body))
+    Ok((status, headers_out, body))
+}
+
+/// Buffers a stored object fully in memory. Used by the encrypted download
+/// path, since `age::Decryptor` needs a seekable-ish, fully-buffered reader
+/// and storage backends only hand back async byte streams.
+async fn read_storage_to_vec(state: &AppState, tenant_id: &str, key: &str) -> Result<Vec<u8>, AppError> {
+    use tokio_stream::StreamExt;
+    let mut stream = state.storage.get(tenant_id, key).await?;
+    let mut buf = Vec::new();
+    while let Some(chunk) = stream.next().await {
+        buf.extend_from_slice(&chunk?);
+    }
+    Ok(buf)
+}
+
+/// Drains a whole-file byte stream into memory so it can be handed to
+/// `state.download_cache` instead of streamed straight to the client. Used
+/// on a cache-enabled, non-`Range` miss in `download`/`public_download`,
+/// after the same chunk-reassembly/verification/decryption machinery that
+/// serves the streaming path has already been applied to `stream`.
+async fn collect_stream_bytes<S>(mut stream: S) -> Result<Bytes, AppError>
+where
+    S: tokio_stream::Stream<Item = std::io::Result<Bytes>> + Unpin,
+{
+    use tokio_stream::StreamExt;
+    let mut buf = Vec::new();
+    while let Some(chunk) = stream.next().await {
+        buf.extend_from_slice(&chunk?);
+    }
+    Ok(Bytes::from(buf))
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -1116,6 +2221,12 @@ struct DownloadTokenPayload {
     tenant_id: String,
     file_id: String,
     exp_ms: i64,
+    /// Random id correlating this token with its `download_tokens` row, so
+    /// a single token can be capped to `max_uses` redemptions or revoked
+    /// before `exp_ms`.
+    jti: String,
+    allowed_ip: Option<String>,
+    capabilities: Vec<String>,
 }
 
 type HmacSha256 = Hmac<Sha256>;
@@ -1161,6 +2272,60 @@ fn verify_token(signing_key: &[u8], token: &str) -> Result<DownloadTokenPayload,
     Ok(payload)
 }
 
+/// Payload of a scoped bearer token minted by `POST /v1/tokens`: a
+/// least-privilege, auto-expiring credential an operator can hand out
+/// without editing `RUSTFS_API_KEYS_JSON` and restarting.
+#[derive(Debug, Serialize, Deserialize)]
+struct ScopedTokenPayload {
+    tenant_id: String,
+    scopes: Vec<String>,
+    exp_ms: i64,
+}
+
+fn sign_scoped_token(signing_key: &[u8], payload: &ScopedTokenPayload) -> Result<String, AppError> {
+    let payload_json =
+        serde_json::to_vec(payload).map_err(|e| AppError::InvalidRequest(e.to_string()))?;
+    let payload_b64 = URL_SAFE_NO_PAD.encode(payload_json);
+    let mut mac = HmacSha256::new_from_slice(signing_key)
+        .map_err(|_| AppError::InvalidRequest("invalid signing key".to_string()))?;
+    mac.update(payload_b64.as_bytes());
+    let sig = mac.finalize().into_bytes();
+    let sig_b64 = URL_SAFE_NO_PAD.encode(sig);
+    Ok(format!("{payload_b64}.{sig_b64}"))
+}
+
+/// Verifies a scoped bearer token, the counterpart of `sign_scoped_token`.
+/// `HmacSha256::verify_slice` compares the computed and provided MACs in
+/// constant time, so rejection never leaks timing information about how
+/// much of the signature matched.
+fn verify_scoped_token(signing_key: &[u8], token: &str) -> Result<ScopedTokenPayload, AppError> {
+    let parts: Vec<&str> = token.split('.').collect();
+    if parts.len() != 2 {
+        return Err(AppError::InvalidRequest("invalid token".to_string()));
+    }
+    let payload_b64 = parts[0];
+    let sig_b64 = parts[1];
+
+    let sig = URL_SAFE_NO_PAD
+        .decode(sig_b64.as_bytes())
+        .map_err(|_| AppError::InvalidRequest("invalid token".to_string()))?;
+
+    let mut mac = HmacSha256::new_from_slice(signing_key)
+        .map_err(|_| AppError::InvalidRequest("invalid signing key".to_string()))?;
+    mac.update(payload_b64.as_bytes());
+    mac.verify_slice(&sig).map_err(|_| AppError::Unauthorized)?;
+
+    let payload_json = URL_SAFE_NO_PAD
+        .decode(payload_b64.as_bytes())
+        .map_err(|_| AppError::InvalidRequest("invalid token".to_string()))?;
+    let payload: ScopedTokenPayload = serde_json::from_slice(&payload_json)
+        .map_err(|_| AppError::InvalidRequest("invalid token".to_string()))?;
+    if payload.exp_ms <= now_ms() {
+        return Err(AppError::Unauthorized);
+    }
+    Ok(payload)
+}
+
 #[derive(Debug, Deserialize)]
 struct PublicDownloadQuery {
     token: String,
@@ -1172,8 +2337,8 @@ async fn create_link(
     axum::extract::Path(path): axum::extract::Path<PathParams>,
     Json(req): Json<LinkRequest>,
 ) -> Result<impl IntoResponse, AppError> {
-    let auth = auth_from_headers(&state, &headers, None)?;
-    assert_can_write(&auth)?;
+    let auth = auth_from_headers(&state, &headers, None).await?;
+    assert_can_write(&auth, "download")?;
 
     let signing_key = state.signing_key.as_deref().ok_or_else(|| {
         AppError::InvalidRequest("RUSTFS_SIGNING_KEY is not configured".to_string())
@@ -1203,12 +2368,38 @@ async fn create_link(
 
     let ttl = req.ttl_seconds.unwrap_or(300).clamp(30, 3600) as i64;
     let expires_at_ms = now_ms() + ttl * 1000;
+    let jti = uuid::Uuid::new_v4().to_string();
+    let allowed_ip = req.allowed_ip.as_deref().map(|s| s.trim().to_string()).filter(|s| !s.is_empty());
+    let capabilities = req
+        .capabilities
+        .clone()
+        .unwrap_or_else(|| vec!["download".to_string()]);
     let payload = DownloadTokenPayload {
         tenant_id: auth.tenant_id.clone(),
         file_id: file_id.clone(),
         exp_ms: expires_at_ms,
+        jti: jti.clone(),
+        allowed_ip: allowed_ip.clone(),
+        capabilities,
     };
     let token = sign_token(signing_key, &payload)?;
+
+    let jti_db = jti.clone();
+    let tenant_id_db = auth.tenant_id.clone();
+    let file_id_db = file_id.clone();
+    let max_uses = req.max_uses.map(|n| n as i64);
+    let created_at_ms = now_ms();
+    with_conn(&state, move |conn| -> Result<(), AppError> {
+        conn.execute(
+            "INSERT INTO download_tokens(jti, tenant_id, file_id, uses_remaining, revoked, exp_ms, created_at_ms)
+             VALUES (?1, ?2, ?3, ?4, 0, ?5, ?6)",
+            params![jti_db, tenant_id_db, file_id_db, max_uses, expires_at_ms, created_at_ms],
+        )
+        .map_err(|e| AppError::Db(e.to_string()))?;
+        Ok(())
+    })
+    .await?;
+
     let path = format!("/v1/public/download?token={}", token);
     let url = state.public_base_url.as_deref().map(|base| {
         let b = base.trim_end_matches('/');
@@ -1225,7 +2416,7 @@ async fn create_link(
             key_id: Some(&auth.key_id),
             request_id,
             file_id: Some(&file_id),
-            extra: serde_json::json!({ "ttl_seconds": ttl }),
+            extra: serde_json::json!({ "ttl_seconds": ttl, "jti": jti, "max_uses": req.max_uses }),
         },
     )
     .await;
@@ -1237,19 +2428,107 @@ async fn create_link(
             path,
             url,
             expires_at_ms,
+            jti,
         }),
     ))
 }
 
+#[derive(Debug, Deserialize)]
+struct FileLinkParams {
+    file_id: String,
+    jti: String,
+}
+
+async fn revoke_link(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Path(path): axum::extract::Path<FileLinkParams>,
+) -> Result<impl IntoResponse, AppError> {
+    let request_id = headers.get("x-request-id").and_then(|v| v.to_str().ok());
+    let auth = auth_from_headers(&state, &headers, None).await?;
+    assert_can_write(&auth, "download")?;
+
+    let tenant_id = auth.tenant_id.clone();
+    let file_id = path.file_id.trim().to_string();
+    let jti = path.jti.trim().to_string();
+    let tenant_id_db = tenant_id.clone();
+    let file_id_db = file_id.clone();
+    let jti_db = jti.clone();
+    let revoked = with_conn(&state, move |conn| -> Result<usize, AppError> {
+        let n = conn
+            .execute(
+                "UPDATE download_tokens SET revoked=1 WHERE jti=?1 AND tenant_id=?2 AND file_id=?3",
+                params![jti_db, tenant_id_db, file_id_db],
+            )
+            .map_err(|e| AppError::Db(e.to_string()))?;
+        Ok(n)
+    })
+    .await?;
+    if revoked == 0 {
+        return Err(AppError::NotFound);
+    }
+
+    append_audit(
+        &state,
+        AuditEntry {
+            id: uuid::Uuid::new_v4().to_string(),
+            ts_ms: now_ms(),
+            action: "link_revoke",
+            tenant_id: &auth.tenant_id,
+            key_id: Some(&auth.key_id),
+            request_id,
+            file_id: Some(&file_id),
+            extra: serde_json::json!({ "jti": jti }),
+        },
+    )
+    .await;
+
+    Ok((StatusCode::OK, Json(serde_json::json!({ "ok": true }))))
+}
+
 async fn public_download(
     State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
     Query(q): Query<PublicDownloadQuery>,
 ) -> Result<impl IntoResponse, AppError> {
     let signing_key = state.signing_key.as_deref().ok_or_else(|| {
         AppError::InvalidRequest("RUSTFS_SIGNING_KEY is not configured".to_string())
     })?;
+    // The HMAC signature (and `exp_ms`) are checked first so a malformed or
+    // forged token never reaches the `download_tokens` table lookup below.
     let payload = verify_token(signing_key, q.token.trim())?;
 
+    if !payload.capabilities.iter().any(|c| c == "download") {
+        return Err(AppError::Forbidden);
+    }
+    if let Some(allowed_ip) = payload.allowed_ip.as_deref() {
+        if allowed_ip != peer.ip().to_string() {
+            return Err(AppError::Forbidden);
+        }
+    }
+
+    // Atomically consume one use: a revoked token, an exhausted `max_uses`,
+    // or a since-expired row (belt-and-suspenders alongside the `exp_ms`
+    // check above) all fail to match, so `redeemed == 0` covers all three.
+    let jti_db = payload.jti.clone();
+    let now = now_ms();
+    let redeemed = with_conn(&state, move |conn| -> Result<usize, AppError> {
+        let n = conn
+            .execute(
+                "UPDATE download_tokens
+                 SET uses_remaining = CASE WHEN uses_remaining IS NULL THEN NULL ELSE uses_remaining - 1 END
+                 WHERE jti=?1 AND revoked=0 AND exp_ms>?2 AND (uses_remaining IS NULL OR uses_remaining > 0)",
+                params![jti_db, now],
+            )
+            .map_err(|e| AppError::Db(e.to_string()))?;
+        Ok(n)
+    })
+    .await?;
+    if redeemed == 0 {
+        return Err(AppError::Unauthorized);
+    }
+
     // Reuse existing download logic by querying metadata and streaming file.
     // This endpoint bypasses API key auth but is constrained by the signed token.
     let tenant_id = payload.tenant_id;
@@ -1274,7 +2553,14 @@ async fn public_download(
         filename: String,
         mime: Option<String>,
         encrypted: bool,
+        chunked: bool,
         storage_path: String,
+        storage_tenant: Option<String>,
+        enc_method: Option<String>,
+        size: i64,
+        sha256: String,
+        wrapped_key: Option<Vec<u8>>,
+        key_version: Option<i64>,
     }
 
     let tenant_id_db = tenant_id.clone();
@@ -1282,7 +2568,7 @@ async fn public_download(
     let row = with_conn(&state, move |conn| -> Result<Option<Row>, AppError> {
         let mut stmt = conn
             .prepare(
-                "SELECT filename, mime, encrypted, storage_path FROM files WHERE tenant_id=?1 AND file_id=?2 AND deleted_at_ms IS NULL",
+                "SELECT filename, mime, encrypted, storage_path, chunked, enc_method, size, sha256, storage_tenant, wrapped_key, key_version FROM files WHERE tenant_id=?1 AND file_id=?2 AND deleted_at_ms IS NULL",
             )
             .map_err(|e| AppError::Db(e.to_string()))?;
         let mut rows = stmt
@@ -1290,11 +2576,19 @@ async fn public_download(
             .map_err(|e| AppError::Db(e.to_string()))?;
         if let Some(row) = rows.next().map_err(|e| AppError::Db(e.to_string()))? {
             let encrypted_i: i64 = row.get(2).map_err(|e| AppError::Db(e.to_string()))?;
+            let chunked_i: i64 = row.get(4).unwrap_or(0);
             return Ok(Some(Row {
                 filename: row.get(0).map_err(|e| AppError::Db(e.to_string()))?,
                 mime: row.get(1).map_err(|e| AppError::Db(e.to_string()))?,
                 encrypted: encrypted_i != 0,
+                chunked: chunked_i != 0,
                 storage_path: row.get(3).map_err(|e| AppError::Db(e.to_string()))?,
+                enc_method: row.get(5).unwrap_or(None),
+                size: row.get(6).map_err(|e| AppError::Db(e.to_string()))?,
+                sha256: row.get(7).map_err(|e| AppError::Db(e.to_string()))?,
+                storage_tenant: row.get(8).unwrap_or(None),
+                wrapped_key: row.get(9).unwrap_or(None),
+                key_version: row.get(10).unwrap_or(None),
             }));
         }
         Ok(None)
@@ -1302,12 +2596,30 @@ async fn public_download(
     .await?
     .ok_or(AppError::NotFound)?;
 
-    let abs = state.data_dir.join(row.storage_path.trim_start_matches('/'));
-    if !abs.exists() {
+    let storage_tenant_id = row.storage_tenant.clone().unwrap_or_else(|| tenant_id.clone());
+
+    if !row.chunked && !state.storage.exists(&storage_tenant_id, &row.storage_path).await? {
         return Err(AppError::NotFound);
     }
 
+    let total_size = row.size.max(0) as u64;
+    let range = match parse_range(
+        headers.get(header::RANGE).and_then(|v| v.to_str().ok()),
+        total_size,
+    ) {
+        Ok(r) => r,
+        Err(()) => {
+            let mut headers_out = HeaderMap::new();
+            headers_out.insert(
+                header::CONTENT_RANGE,
+                format!("bytes */{total_size}").parse().unwrap(),
+            );
+            return Ok((StatusCode::RANGE_NOT_SATISFIABLE, headers_out, Body::empty()));
+        }
+    };
+
     let mut headers_out = HeaderMap::new();
+    headers_out.insert(header::ACCEPT_RANGES, "bytes".parse().unwrap());
     headers_out.insert(
         header::CONTENT_DISPOSITION,
         format!("attachment; filename=\"{}\"", row.filename.replace('"', "_"))
@@ -1319,47 +2631,174 @@ async fn public_download(
             headers_out.insert(header::CONTENT_TYPE, v);
         }
     }
+    let status = if let Some((start, end)) = range {
+        headers_out.insert(
+            header::CONTENT_RANGE,
+            format!("bytes {start}-{end}/{total_size}").parse().unwrap(),
+        );
+        headers_out.insert(header::CONTENT_LENGTH, (end - start + 1).to_string().parse().unwrap());
+        StatusCode::PARTIAL_CONTENT
+    } else {
+        StatusCode::OK
+    };
+
+    if range.is_none() {
+        if let Some(cached) = state.download_cache.get(&file_id) {
+            headers_out.insert(header::CONTENT_LENGTH, cached.bytes.len().to_string().parse().unwrap());
+            return Ok((status, headers_out, Body::from(cached.bytes)));
+        }
+    }
+    let cache_this_download = range.is_none() && state.download_cache.is_enabled();
+
+    // Integrity verification streams the whole file against its stored
+    // digest; a range request only ever sees a slice, which can never equal
+    // the full-file sha256, so it's skipped for ranged requests.
+    if row.chunked {
+        let stream = reassemble_chunks(&state, &tenant_id, &file_id).await?;
+        if cache_this_download {
+            let stream = verify_stream(stream, row.sha256.clone(), state.clone(), tenant_id.clone(), file_id.clone());
+            let bytes = collect_stream_bytes(stream).await?;
+            state.download_cache.insert(
+                file_id.clone(),
+                cache::CachedDownload { bytes: bytes.clone() },
+            );
+            headers_out.insert(header::CONTENT_LENGTH, bytes.len().to_string().parse().unwrap());
+            return Ok((status, headers_out, Body::from(bytes)));
+        }
+        let stream = match range {
+            Some((start, end)) => storage::bound_stream(stream, start, end - start + 1),
+            None => verify_stream(stream, row.sha256.clone(), state.clone(), tenant_id.clone(), file_id.clone()),
+        };
+        return Ok((status, headers_out, Body::from_stream(stream)));
+    }
 
     if !row.encrypted {
-        let file = fs::File::open(abs).await?;
-        let body = Body::from_stream(ReaderStream::new(file));
-        return Ok((StatusCode::OK, headers_out, body));
+        if cache_this_download {
+            let stream = state.storage.get(&storage_tenant_id, &row.storage_path).await?;
+            let stream = verify_stream(stream, row.sha256.clone(), state.clone(), tenant_id.clone(), file_id.clone());
+            let bytes = collect_stream_bytes(stream).await?;
+            state.download_cache.insert(
+                file_id.clone(),
+                cache::CachedDownload { bytes: bytes.clone() },
+            );
+            headers_out.insert(header::CONTENT_LENGTH, bytes.len().to_string().parse().unwrap());
+            return Ok((status, headers_out, Body::from(bytes)));
+        }
+        let stream = match range {
+            Some((start, end)) => {
+                state
+                    .storage
+                    .get_range(&storage_tenant_id, &row.storage_path, start, end - start + 1)
+                    .await?
+            }
+            None => {
+                let stream = state.storage.get(&storage_tenant_id, &row.storage_path).await?;
+                verify_stream(stream, row.sha256.clone(), state.clone(), tenant_id.clone(), file_id.clone())
+            }
+        };
+        return Ok((status, headers_out, Body::from_stream(stream)));
     }
 
-    let key = state
-        .master_key
-        .clone()
-        .ok_or_else(|| AppError::Crypto("encrypted file but no master key configured".to_string()))?;
+    let enc_method = crypto::EncMethod::from_db_str(row.enc_method.as_deref());
+    let passphrase_key =
+        resolve_passphrase_key(&state, &enc_method, row.wrapped_key.clone(), row.key_version).await?;
+    let identity = crypto::resolve_identity(
+        &enc_method,
+        &tenant_id,
+        passphrase_key,
+        &state.tenant_identities,
+        headers.get("x-age-identity").and_then(|v| v.to_str().ok()),
+    )?;
+    let ciphertext = read_storage_to_vec(&state, &storage_tenant_id, &row.storage_path).await?;
+    let (skip, mut take) = match range {
+        Some((start, end)) => (start, end - start + 1),
+        None => (0, total_size),
+    };
+    let verify_expected = if range.is_none() { Some(row.sha256.clone()) } else { None };
     let (tx, rx) = tokio::sync::mpsc::channel::<Result<Bytes, std::io::Error>>(8);
+    let state_for_audit = state.clone();
+    let tenant_id_for_audit = tenant_id.clone();
+    let file_id_for_audit = file_id.clone();
     tokio::task::spawn_blocking(move || {
-        let result: Result<(), std::io::Error> = (|| {
-            let input = std::fs::File::open(abs)?;
-            let decryptor = age::Decryptor::new(std::io::BufReader::new(input))
+        let result: Result<Option<String>, std::io::Error> = (|| {
+            let decryptor = age::Decryptor::new(std::io::Cursor::new(ciphertext))
                 .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))?;
-            let identity = age::scrypt::Identity::new(key);
             let mut reader = decryptor
-                .decrypt(iter::once(&identity as &dyn age::Identity))
+                .decrypt(iter::once(identity.as_ref()))
                 .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))?;
 
             let mut buf = vec![0u8; 64 * 1024];
-            loop {
-                let n = reader.read(&mut buf)?;
+            let mut skip = skip;
+            while skip > 0 {
+                let want = skip.min(buf.len() as u64) as usize;
+                let n = reader.read(&mut buf[..want])?;
+                if n == 0 {
+                    break;
+                }
+                skip -= n as u64;
+            }
+            let mut hasher = verify_expected.as_ref().map(|_| Sha256::new());
+            while take > 0 {
+                let want = take.min(buf.len() as u64) as usize;
+                let n = reader.read(&mut buf[..want])?;
                 if n == 0 {
                     break;
                 }
+                take -= n as u64;
+                if let Some(hasher) = hasher.as_mut() {
+                    hasher.update(&buf[..n]);
+                }
                 if tx.blocking_send(Ok(Bytes::copy_from_slice(&buf[..n]))).is_err() {
                     break;
                 }
             }
-            Ok(())
+            if let (Some(expected), Some(hasher)) = (verify_expected, hasher) {
+                let actual = hex::encode(hasher.finalize());
+                if actual != expected {
+                    return Ok(Some(actual));
+                }
+            }
+            Ok(None)
         })();
-        if let Err(e) = result {
-            let _ = tx.blocking_send(Err(e));
+        match result {
+            Ok(Some(actual_sha256)) => {
+                let handle = tokio::runtime::Handle::current();
+                handle.block_on(append_audit(
+                    &state_for_audit,
+                    AuditEntry {
+                        id: uuid::Uuid::new_v4().to_string(),
+                        ts_ms: now_ms(),
+                        action: "integrity_mismatch",
+                        tenant_id: &tenant_id_for_audit,
+                        key_id: None,
+                        request_id: None,
+                        file_id: Some(&file_id_for_audit),
+                        extra: serde_json::json!({ "actual_sha256": actual_sha256 }),
+                    },
+                ));
+                let _ = tx.blocking_send(Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    "integrity check failed",
+                )));
+            }
+            Ok(None) => {}
+            Err(e) => {
+                let _ = tx.blocking_send(Err(e));
+            }
         }
     });
 
+    if cache_this_download {
+        let bytes = collect_stream_bytes(ReceiverStream::new(rx)).await?;
+        state.download_cache.insert(
+            file_id.clone(),
+            cache::CachedDownload { bytes: bytes.clone() },
+        );
+        headers_out.insert(header::CONTENT_LENGTH, bytes.len().to_string().parse().unwrap());
+        return Ok((status, headers_out, Body::from(bytes)));
+    }
     let body = Body::from_stream(ReceiverStream::new(rx));
-    Ok((StatusCode::OK, headers_out, body))
+    Ok((status, headers_out, body))
 }
 
 async fn tombstone(
@@ -1369,15 +2808,43 @@ async fn tombstone(
     Json(req): Json<TombstoneRequest>,
 ) -> Result<impl IntoResponse, AppError> {
     let request_id = headers.get("x-request-id").and_then(|v| v.to_str().ok());
-    let auth = auth_from_headers(&state, &headers, None)?;
-    assert_can_write(&auth)?;
+    let auth = auth_from_headers(&state, &headers, None).await?;
+    assert_can_write(&auth, "ingest")?;
     let file_id = path.file_id.trim().to_string();
     if file_id.is_empty() {
         return Err(AppError::InvalidRequest("file_id required".to_string()));
     }
     let tenant_id = auth.tenant_id.clone();
+    let tenant_id_for_gc = tenant_id.clone();
+    let tenant_id_for_select = tenant_id.clone();
     let file_id_db = file_id.clone();
+    let file_id_for_gc = file_id.clone();
+    let file_id_for_select = file_id.clone();
     let ts = now_ms();
+
+    struct GcInfo {
+        chunked: bool,
+        storage_tenant: Option<String>,
+        sha256: String,
+    }
+    let gc_info = with_conn(&state, move |conn| -> Result<Option<GcInfo>, AppError> {
+        conn.query_row(
+            "SELECT chunked, storage_tenant, sha256 FROM files WHERE tenant_id=?1 AND file_id=?2 AND deleted_at_ms IS NULL",
+            params![tenant_id_for_select, file_id_for_select],
+            |row| {
+                let chunked_i: i64 = row.get(0)?;
+                Ok(GcInfo {
+                    chunked: chunked_i != 0,
+                    storage_tenant: row.get(1)?,
+                    sha256: row.get(2)?,
+                })
+            },
+        )
+        .optional()
+        .map_err(|e| AppError::Db(e.to_string()))
+    })
+    .await?;
+
     let updated = with_conn(&state, move |conn| -> Result<usize, AppError> {
         let n = conn
             .execute(
@@ -1389,6 +2856,16 @@ async fn tombstone(
     })
     .await?;
     let tombstoned = updated > 0;
+    if tombstoned {
+        if let Some(info) = gc_info {
+            if info.chunked {
+                release_chunks(&state, &tenant_id_for_gc, &file_id_for_gc).await?;
+            } else if info.storage_tenant.as_deref() == Some(BLOB_TENANT) {
+                release_blob(&state, &info.sha256).await?;
+            }
+        }
+        state.download_cache.invalidate(&file_id);
+    }
     append_audit(
         &state,
         AuditEntry {
@@ -1413,122 +2890,1114 @@ async fn tombstone(
     ))
 }
 
-fn parse_api_keys_json(raw: &str) -> HashMap<String, ApiKey> {
-    let trimmed = raw.trim();
-    if trimmed.is_empty() {
-        return HashMap::new();
-    }
-    let parsed: serde_json::Value = match serde_json::from_str(trimmed) {
-        Ok(v) => v,
-        Err(_) => return HashMap::new(),
-    };
-    let mut map = HashMap::new();
-    let arr = parsed.as_array().cloned().unwrap_or_default();
-    for item in arr {
-        let key = item.get("key").and_then(|v| v.as_str()).unwrap_or("").trim().to_string();
-        let tenant_id = item
-            .get("tenant_id")
-            .and_then(|v| v.as_str())
-            .unwrap_or("")
-            .trim()
-            .to_string();
-        let role = item.get("role").and_then(|v| v.as_str()).map(|s| s.to_string());
-        if key.is_empty() || tenant_id.is_empty() {
-            continue;
-        }
-        map.insert(
-            key.clone(),
-            ApiKey {
-                key,
-                tenant_id,
-                role,
-            },
-        );
-    }
-    map
+#[derive(Debug, Serialize)]
+struct VerifyResponse {
+    ok: bool,
+    file_id: String,
+    verify_status: String,
+    verified_at_ms: i64,
 }
 
-#[tokio::main]
-async fn main() -> Result<(), anyhow::Error> {
-    tracing_subscriber::fmt()
-        .with_env_filter(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "rustfs=info,tower_http=warn".into()),
-        )
-        .init();
+/// Reads a stream to completion and hashes it, without forwarding any bytes
+/// anywhere; the server-side counterpart of `verify_stream`'s inline check.
+async fn hash_stream(stream: &mut storage::ByteStream) -> Result<String, AppError> {
+    use tokio_stream::StreamExt;
+    let mut hasher = Sha256::new();
+    while let Some(chunk) = stream.next().await {
+        hasher.update(&chunk?);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
 
-    let port: u16 = std::env::var("RUSTFS_PORT")
-        .ok()
-        .and_then(|v| v.parse().ok())
-        .unwrap_or(8099);
-    let data_dir = std::env::var("RUSTFS_DATA_DIR").unwrap_or_else(|_| "/data".to_string());
-    let db_path = std::env::var("RUSTFS_DB_PATH").unwrap_or_else(|_| "/data/meta.db".to_string());
-    let require_api_key = std::env::var("RUSTFS_REQUIRE_API_KEY")
-        .ok()
-        .map(|v| v.trim().to_lowercase() == "true" || v.trim() == "1")
-        .unwrap_or(true);
-    let api_keys_json = std::env::var("RUSTFS_API_KEYS_JSON").unwrap_or_default();
-    let master_key_raw = std::env::var("RUSTFS_MASTER_KEY").ok().map(|v| v.trim().to_string());
-    let master_key = master_key_raw
-        .as_deref()
-        .filter(|s| !s.is_empty())
-        .map(|s| SecretString::from(s.to_string()));
-    let signing_key = std::env::var("RUSTFS_SIGNING_KEY")
-        .ok()
-        .map(|v| v.trim().as_bytes().to_vec())
-        .filter(|v| !v.is_empty());
-    let public_base_url = std::env::var("RUSTFS_PUBLIC_BASE_URL")
-        .ok()
-        .map(|v| v.trim().to_string())
-        .filter(|v| !v.is_empty());
-    let audit_log_path = std::env::var("RUSTFS_AUDIT_LOG_PATH")
-        .ok()
-        .map(|v| v.trim().to_string())
-        .filter(|v| !v.is_empty())
-        .map(PathBuf::from);
+/// Re-reads and re-hashes a stored file server-side, without streaming it to
+/// the caller, and records the outcome on `files.verify_status` /
+/// `files.verified_at_ms` so a scrub job can sweep the store periodically
+/// instead of relying on a client happening to download every file.
+async fn verify_file(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Path(path): axum::extract::Path<PathParams>,
+) -> Result<impl IntoResponse, AppError> {
+    let request_id = headers.get("x-request-id").and_then(|v| v.to_str().ok());
+    let auth = auth_from_headers(&state, &headers, None).await?;
+    assert_can_read(&auth, "download")?;
+    let tenant_id = auth.tenant_id.clone();
+    let file_id = path.file_id.trim().to_string();
+    if file_id.is_empty() {
+        return Err(AppError::InvalidRequest("file_id required".to_string()));
+    }
+
+    #[derive(Debug)]
+    struct Row {
+        encrypted: bool,
+        chunked: bool,
+        storage_path: String,
+        storage_tenant: Option<String>,
+        enc_method: Option<String>,
+        sha256: String,
+        wrapped_key: Option<Vec<u8>>,
+        key_version: Option<i64>,
+    }
+
+    let tenant_id_db = tenant_id.clone();
+    let file_id_db = file_id.clone();
+    let row = with_conn(&state, move |conn| -> Result<Option<Row>, AppError> {
+        let mut stmt = conn
+            .prepare(
+                "SELECT encrypted, storage_path, chunked, enc_method, sha256, storage_tenant, wrapped_key, key_version FROM files WHERE tenant_id=?1 AND file_id=?2 AND deleted_at_ms IS NULL",
+            )
+            .map_err(|e| AppError::Db(e.to_string()))?;
+        let mut rows = stmt
+            .query(params![tenant_id_db, file_id_db])
+            .map_err(|e| AppError::Db(e.to_string()))?;
+        if let Some(row) = rows.next().map_err(|e| AppError::Db(e.to_string()))? {
+            let encrypted_i: i64 = row.get(0).map_err(|e| AppError::Db(e.to_string()))?;
+            let chunked_i: i64 = row.get(2).unwrap_or(0);
+            return Ok(Some(Row {
+                encrypted: encrypted_i != 0,
+                storage_path: row.get(1).map_err(|e| AppError::Db(e.to_string()))?,
+                chunked: chunked_i != 0,
+                enc_method: row.get(3).unwrap_or(None),
+                sha256: row.get(4).map_err(|e| AppError::Db(e.to_string()))?,
+                storage_tenant: row.get(5).unwrap_or(None),
+                wrapped_key: row.get(6).unwrap_or(None),
+                key_version: row.get(7).unwrap_or(None),
+            }));
+        }
+        Ok(None)
+    })
+    .await?
+    .ok_or(AppError::NotFound)?;
+
+    let storage_tenant_id = row.storage_tenant.clone().unwrap_or_else(|| tenant_id.clone());
+
+    let actual_sha256 = if row.chunked {
+        let mut stream = reassemble_chunks(&state, &tenant_id, &file_id).await?;
+        hash_stream(&mut stream).await?
+    } else if !row.encrypted {
+        let mut stream = state.storage.get(&storage_tenant_id, &row.storage_path).await?;
+        hash_stream(&mut stream).await?
+    } else {
+        let enc_method = crypto::EncMethod::from_db_str(row.enc_method.as_deref());
+        let passphrase_key =
+            resolve_passphrase_key(&state, &enc_method, row.wrapped_key.clone(), row.key_version).await?;
+        let identity = crypto::resolve_identity(
+            &enc_method,
+            &tenant_id,
+            passphrase_key,
+            &state.tenant_identities,
+            headers.get("x-age-identity").and_then(|v| v.to_str().ok()),
+        )?;
+        let ciphertext = read_storage_to_vec(&state, &storage_tenant_id, &row.storage_path).await?;
+        tokio::task::spawn_blocking(move || -> Result<String, AppError> {
+            let decryptor = age::Decryptor::new(std::io::Cursor::new(ciphertext))
+                .map_err(|e| AppError::Crypto(e.to_string()))?;
+            let mut reader = decryptor
+                .decrypt(iter::once(identity.as_ref()))
+                .map_err(|e| AppError::Crypto(e.to_string()))?;
+            let mut hasher = Sha256::new();
+            let mut buf = vec![0u8; 64 * 1024];
+            loop {
+                let n = reader.read(&mut buf)?;
+                if n == 0 {
+                    break;
+                }
+                hasher.update(&buf[..n]);
+            }
+            Ok(hex::encode(hasher.finalize()))
+        })
+        .await
+        .map_err(|e| AppError::Crypto(e.to_string()))??
+    };
+
+    let verify_status = if actual_sha256 == row.sha256 { "ok" } else { "mismatch" };
+    let verified_at_ms = now_ms();
+
+    let tenant_id_db = tenant_id.clone();
+    let file_id_db = file_id.clone();
+    let verify_status_db = verify_status.to_string();
+    with_conn(&state, move |conn| -> Result<(), AppError> {
+        conn.execute(
+            "UPDATE files SET verified_at_ms=?1, verify_status=?2 WHERE tenant_id=?3 AND file_id=?4",
+            params![verified_at_ms, verify_status_db, tenant_id_db, file_id_db],
+        )
+        .map_err(|e| AppError::Db(e.to_string()))?;
+        Ok(())
+    })
+    .await?;
+
+    if verify_status == "mismatch" {
+        append_audit(
+            &state,
+            AuditEntry {
+                id: uuid::Uuid::new_v4().to_string(),
+                ts_ms: now_ms(),
+                action: "integrity_mismatch",
+                tenant_id: &tenant_id,
+                key_id: Some(&auth.key_id),
+                request_id,
+                file_id: Some(&file_id),
+                extra: serde_json::json!({ "expected_sha256": row.sha256, "actual_sha256": actual_sha256 }),
+            },
+        )
+        .await;
+    }
+
+    Ok((
+        StatusCode::OK,
+        Json(VerifyResponse {
+            ok: verify_status == "ok",
+            file_id,
+            verify_status: verify_status.to_string(),
+            verified_at_ms,
+        }),
+    ))
+}
+
+#[derive(Debug, Deserialize)]
+struct CreateApiKeyRequest {
+    tenant_id: String,
+    role: Option<String>,
+    expires_at_ms: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+struct CreateApiKeyResponse {
+    ok: bool,
+    key_id: String,
+    /// Returned only on creation; the server keeps just the SHA-256 hash.
+    api_key: String,
+    tenant_id: String,
+    role: String,
+    expires_at_ms: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+struct ApiKeySummary {
+    key_id: String,
+    tenant_id: String,
+    role: String,
+    disabled: bool,
+    created_at_ms: i64,
+    expires_at_ms: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+struct ListApiKeysResponse {
+    ok: bool,
+    items: Vec<ApiKeySummary>,
+}
+
+async fn create_api_key(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(req): Json<CreateApiKeyRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let request_id = headers.get("x-request-id").and_then(|v| v.to_str().ok());
+    let auth = auth_from_headers(&state, &headers, None).await?;
+    assert_is_admin(&auth)?;
+
+    let tenant_id = req.tenant_id.trim().to_string();
+    if tenant_id.is_empty() {
+        return Err(AppError::InvalidRequest("tenant_id required".to_string()));
+    }
+    // An admin creates keys for their own tenant only; this is the one
+    // endpoint that mints new admin keys, so failing to scope it would
+    // undo the scoping added everywhere else.
+    if tenant_id != auth.tenant_id {
+        return Err(AppError::Forbidden);
+    }
+    let role = normalize_role(req.role.as_deref());
+    let raw_key = format!("sk_{}", uuid::Uuid::new_v4().simple());
+    let key_hash = full_key_hash(&raw_key);
+    let key_id = key_id_from_raw(&raw_key);
+    let created_at_ms = now_ms();
+
+    let key_hash_db = key_hash.clone();
+    let tenant_id_db = tenant_id.clone();
+    let role_db = role.clone();
+    let expires_at_ms = req.expires_at_ms;
+    with_conn(&state, move |conn| -> Result<(), AppError> {
+        conn.execute(
+            "INSERT INTO api_keys(key_hash, tenant_id, role, disabled, created_at_ms, expires_at_ms)
+             VALUES (?1, ?2, ?3, 0, ?4, ?5)",
+            params![key_hash_db, tenant_id_db, role_db, created_at_ms, expires_at_ms],
+        )
+        .map_err(|e| AppError::Db(e.to_string()))?;
+        Ok(())
+    })
+    .await?;
+
+    append_audit(
+        &state,
+        AuditEntry {
+            id: uuid::Uuid::new_v4().to_string(),
+            ts_ms: now_ms(),
+            action: "api_key_create",
+            tenant_id: &auth.tenant_id,
+            key_id: Some(&auth.key_id),
+            request_id,
+            file_id: None,
+            extra: serde_json::json!({ "created_key_id": key_id, "created_tenant_id": tenant_id }),
+        },
+    )
+    .await;
+
+    Ok((
+        StatusCode::OK,
+        Json(CreateApiKeyResponse {
+            ok: true,
+            key_id,
+            api_key: raw_key,
+            tenant_id,
+            role,
+            expires_at_ms,
+        }),
+    ))
+}
+
+async fn list_api_keys(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, AppError> {
+    let auth = auth_from_headers(&state, &headers, None).await?;
+    assert_is_admin(&auth)?;
+
+    // An admin administers their own tenant, not the whole deployment: list
+    // only keys belonging to `auth.tenant_id`.
+    let tenant_id = auth.tenant_id.clone();
+    let items = with_conn(&state, move |conn| -> Result<Vec<ApiKeySummary>, AppError> {
+        let mut stmt = conn
+            .prepare(
+                "SELECT key_hash, tenant_id, role, disabled, created_at_ms, expires_at_ms FROM api_keys WHERE tenant_id=?1 ORDER BY created_at_ms DESC",
+            )
+            .map_err(|e| AppError::Db(e.to_string()))?;
+        let mut rows = stmt.query(params![tenant_id]).map_err(|e| AppError::Db(e.to_string()))?;
+        let mut out = Vec::new();
+        while let Some(row) = rows.next().map_err(|e| AppError::Db(e.to_string()))? {
+            let key_hash: String = row.get(0).map_err(|e| AppError::Db(e.to_string()))?;
+            let disabled: i64 = row.get(3).map_err(|e| AppError::Db(e.to_string()))?;
+            out.push(ApiKeySummary {
+                key_id: key_hash[..16.min(key_hash.len())].to_string(),
+                tenant_id: row.get(1).map_err(|e| AppError::Db(e.to_string()))?,
+                role: row.get(2).map_err(|e| AppError::Db(e.to_string()))?,
+                disabled: disabled != 0,
+                created_at_ms: row.get(4).map_err(|e| AppError::Db(e.to_string()))?,
+                expires_at_ms: row.get(5).map_err(|e| AppError::Db(e.to_string()))?,
+            });
+        }
+        Ok(out)
+    })
+    .await?;
+
+    Ok((StatusCode::OK, Json(ListApiKeysResponse { ok: true, items })))
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiKeyIdParam {
+    key_id: String,
+}
+
+async fn revoke_api_key(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Path(path): axum::extract::Path<ApiKeyIdParam>,
+) -> Result<impl IntoResponse, AppError> {
+    let request_id = headers.get("x-request-id").and_then(|v| v.to_str().ok());
+    let auth = auth_from_headers(&state, &headers, None).await?;
+    assert_is_admin(&auth)?;
+
+    let key_id = path.key_id.trim().to_string();
+    if key_id.is_empty() {
+        return Err(AppError::InvalidRequest("key_id required".to_string()));
+    }
+    let key_id_like = format!("{key_id}%");
+    // An admin can only revoke keys belonging to their own tenant.
+    let tenant_id = auth.tenant_id.clone();
+    let updated = with_conn(&state, move |conn| -> Result<usize, AppError> {
+        conn.execute(
+            "UPDATE api_keys SET disabled=1 WHERE key_hash LIKE ?1 AND tenant_id=?2",
+            params![key_id_like, tenant_id],
+        )
+        .map_err(|e| AppError::Db(e.to_string()))
+    })
+    .await?;
+    if updated == 0 {
+        return Err(AppError::NotFound);
+    }
+
+    append_audit(
+        &state,
+        AuditEntry {
+            id: uuid::Uuid::new_v4().to_string(),
+            ts_ms: now_ms(),
+            action: "api_key_revoke",
+            tenant_id: &auth.tenant_id,
+            key_id: Some(&auth.key_id),
+            request_id,
+            file_id: None,
+            extra: serde_json::json!({ "revoked_key_id": key_id }),
+        },
+    )
+    .await;
+
+    Ok((StatusCode::OK, Json(serde_json::json!({ "ok": true }))))
+}
+
+#[derive(Debug, Deserialize)]
+struct TenantIdParam {
+    tenant_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct AddTenantRecipientRequest {
+    recipient: String,
+}
+
+/// Registers an age X25519 recipient for a tenant; `ingest` encrypts new
+/// uploads for that tenant against every registered recipient instead of the
+/// shared `RUSTFS_MASTER_KEY` passphrase once at least one is registered.
+async fn add_tenant_recipient(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Path(path): axum::extract::Path<TenantIdParam>,
+    Json(req): Json<AddTenantRecipientRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let request_id = headers.get("x-request-id").and_then(|v| v.to_str().ok());
+    let auth = auth_from_headers(&state, &headers, None).await?;
+    assert_is_admin(&auth)?;
+
+    let tenant_id = path.tenant_id.trim().to_string();
+    let recipient = req.recipient.trim().to_string();
+    if tenant_id.is_empty() || recipient.is_empty() {
+        return Err(AppError::InvalidRequest("tenant_id and recipient required".to_string()));
+    }
+    // An admin registers recipients for their own tenant only.
+    if tenant_id != auth.tenant_id {
+        return Err(AppError::Forbidden);
+    }
+    recipient
+        .parse::<age::x25519::Recipient>()
+        .map_err(|e| AppError::InvalidRequest(format!("invalid age recipient: {e}")))?;
+
+    let tenant_id_db = tenant_id.clone();
+    let recipient_db = recipient.clone();
+    let created_at_ms = now_ms();
+    with_conn(&state, move |conn| -> Result<(), AppError> {
+        conn.execute(
+            "INSERT INTO tenant_recipients(tenant_id, recipient, created_at_ms) VALUES (?1, ?2, ?3)",
+            params![tenant_id_db, recipient_db, created_at_ms],
+        )
+        .map_err(|e| AppError::Db(e.to_string()))?;
+        Ok(())
+    })
+    .await?;
+
+    append_audit(
+        &state,
+        AuditEntry {
+            id: uuid::Uuid::new_v4().to_string(),
+            ts_ms: now_ms(),
+            action: "tenant_recipient_add",
+            tenant_id: &auth.tenant_id,
+            key_id: Some(&auth.key_id),
+            request_id,
+            file_id: None,
+            extra: serde_json::json!({ "target_tenant_id": tenant_id }),
+        },
+    )
+    .await;
+
+    Ok((StatusCode::OK, Json(serde_json::json!({ "ok": true }))))
+}
+
+#[derive(Debug, Serialize)]
+struct SweepBlobsResponse {
+    ok: bool,
+    swept: usize,
+}
+
+/// Physically deletes any blob whose refcount has already reached zero.
+///
+/// `release_blob` deletes eagerly as soon as a refcount hits zero, so in the
+/// steady state this finds nothing; it exists as a backstop for rows left
+/// over from a crash between the refcount decrement and the delete, or from
+/// manual DB surgery. There's no background scheduler in this process, so
+/// (like `pending_extract`) it's exposed as an admin endpoint an operator or
+/// external cron can invoke instead.
+///
+/// Deliberately not tenant-scoped: the content-addressed blob store (`BLOB_TENANT`)
+/// is shared and deduplicated across every tenant by design, so a blob has no
+/// single owning tenant to scope this to.
+async fn sweep_blobs(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, AppError> {
+    let request_id = headers.get("x-request-id").and_then(|v| v.to_str().ok());
+    let auth = auth_from_headers(&state, &headers, None).await?;
+    assert_is_admin(&auth)?;
+
+    let stale = with_conn(&state, move |conn| -> Result<Vec<String>, AppError> {
+        let mut stmt = conn
+            .prepare("SELECT sha256 FROM blobs WHERE refcount <= 0")
+            .map_err(|e| AppError::Db(e.to_string()))?;
+        let mut rows = stmt.query([]).map_err(|e| AppError::Db(e.to_string()))?;
+        let mut out = Vec::new();
+        while let Some(row) = rows.next().map_err(|e| AppError::Db(e.to_string()))? {
+            out.push(row.get(0).map_err(|e| AppError::Db(e.to_string()))?);
+        }
+        Ok(out)
+    })
+    .await?;
+
+    let swept = stale.len();
+    for sha256 in stale {
+        state.storage.delete(BLOB_TENANT, &blob_key(&sha256)).await?;
+        let sha256_db = sha256.clone();
+        with_conn(&state, move |conn| -> Result<(), AppError> {
+            conn.execute("DELETE FROM blobs WHERE sha256=?1", params![sha256_db])
+                .map_err(|e| AppError::Db(e.to_string()))?;
+            Ok(())
+        })
+        .await?;
+    }
+
+    append_audit(
+        &state,
+        AuditEntry {
+            id: uuid::Uuid::new_v4().to_string(),
+            ts_ms: now_ms(),
+            action: "sweep_blobs",
+            tenant_id: &auth.tenant_id,
+            key_id: Some(&auth.key_id),
+            request_id,
+            file_id: None,
+            extra: serde_json::json!({ "swept": swept }),
+        },
+    )
+    .await;
+
+    Ok((StatusCode::OK, Json(SweepBlobsResponse { ok: true, swept })))
+}
+
+#[derive(Debug, Deserialize)]
+struct RotateKeyRequest {
+    old_master_key: String,
+    new_master_key: String,
+}
+
+#[derive(Debug, Serialize)]
+struct RotateKeyResponse {
+    ok: bool,
+    previous_version: i64,
+    new_version: i64,
+    files_rewrapped: usize,
+    blobs_rewrapped: usize,
+}
+
+/// Rewraps every `wrapped_key` in `table` still at `old_version` onto
+/// `new_version`, by unwrapping under `old_key` and wrapping under `new_key`.
+/// Blob ciphertext (and `files.storage_path`/`age`-encrypted payloads) is
+/// never touched — only the small wrapped data key moves, which is what
+/// keeps rotation cheap regardless of how much data is stored.
+async fn rewrap_keys(
+    state: &AppState,
+    table: &'static str,
+    id_column: &'static str,
+    old_version: i64,
+    new_version: i64,
+    old_key: &SecretString,
+    new_key: &SecretString,
+) -> Result<usize, AppError> {
+    let select = format!("SELECT {id_column}, wrapped_key FROM {table} WHERE key_version=?1 AND wrapped_key IS NOT NULL");
+    let rows = with_conn(state, move |conn| -> Result<Vec<(String, Vec<u8>)>, AppError> {
+        let mut stmt = conn.prepare(&select).map_err(|e| AppError::Db(e.to_string()))?;
+        let mut rows = stmt
+            .query(params![old_version])
+            .map_err(|e| AppError::Db(e.to_string()))?;
+        let mut out = Vec::new();
+        while let Some(row) = rows.next().map_err(|e| AppError::Db(e.to_string()))? {
+            out.push((
+                row.get(0).map_err(|e| AppError::Db(e.to_string()))?,
+                row.get(1).map_err(|e| AppError::Db(e.to_string()))?,
+            ));
+        }
+        Ok(out)
+    })
+    .await?;
+
+    let rewrapped = rows.len();
+    let update = format!("UPDATE {table} SET wrapped_key=?1, key_version=?2 WHERE {id_column}=?3");
+    for (id, wrapped) in rows {
+        let data_key = crypto::unwrap_data_key(&wrapped, old_key.clone())?;
+        let new_wrapped = crypto::wrap_data_key(&data_key, new_key.clone())?;
+        let update = update.clone();
+        with_conn(state, move |conn| -> Result<(), AppError> {
+            conn.execute(&update, params![new_wrapped, new_version, id])
+                .map_err(|e| AppError::Db(e.to_string()))?;
+            Ok(())
+        })
+        .await?;
+    }
+    Ok(rewrapped)
+}
+
+/// Rotates the master key: verifies `old_master_key` against the ring's
+/// current key, promotes `new_master_key` to current (retiring the old one
+/// so files not yet rewrapped stay decryptable), then rewraps every `files`
+/// and `blobs` row still on the old version onto the new one.
+///
+/// Deliberately not tenant-scoped: there is one `MasterKeyRing` for the whole
+/// deployment, not one per tenant, so any admin key can invoke this. Unlike
+/// `list_api_keys`/`revoke_api_key`/`add_tenant_recipient`/`mint_token`,
+/// scoping it to "the caller's own tenant" has no meaning here.
+///
+/// The `MasterKeyRing` write lock is held for the whole operation, not just
+/// the version bump: `ingest` reads `current_version`/`current` under a read
+/// lock to decide what to wrap new uploads under, so releasing the write
+/// lock before the rewrap sweep finished would let a new upload land on the
+/// new version while the sweep below still assumes `old_version` rows are
+/// the only ones pending.
+async fn rotate_key(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(req): Json<RotateKeyRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let request_id = headers.get("x-request-id").and_then(|v| v.to_str().ok());
+    let auth = auth_from_headers(&state, &headers, None).await?;
+    assert_is_admin(&auth)?;
+
+    let old_master_key = req.old_master_key.trim().to_string();
+    let new_master_key = req.new_master_key.trim().to_string();
+    if old_master_key.is_empty() || new_master_key.is_empty() {
+        return Err(AppError::InvalidRequest(
+            "old_master_key and new_master_key required".to_string(),
+        ));
+    }
+
+    let ring = state
+        .master_keys
+        .as_ref()
+        .ok_or_else(|| AppError::InvalidRequest("RUSTFS_MASTER_KEY is not configured".to_string()))?;
+    let mut guard = ring.write().await;
+    if guard.current.expose_secret() != old_master_key {
+        return Err(AppError::Unauthorized);
+    }
+
+    let previous_version = guard.current_version;
+    let new_version = previous_version + 1;
+    let old_key = guard.current.clone();
+    let new_key = SecretString::from(new_master_key);
+
+    guard.retired.insert(previous_version, old_key.clone());
+    guard.current = new_key.clone();
+    guard.current_version = new_version;
+
+    let files_rewrapped = rewrap_keys(
+        &state,
+        "files",
+        "file_id",
+        previous_version,
+        new_version,
+        &old_key,
+        &new_key,
+    )
+    .await?;
+    let blobs_rewrapped = rewrap_keys(
+        &state,
+        "blobs",
+        "sha256",
+        previous_version,
+        new_version,
+        &old_key,
+        &new_key,
+    )
+    .await?;
+
+    drop(guard);
+
+    append_audit(
+        &state,
+        AuditEntry {
+            id: uuid::Uuid::new_v4().to_string(),
+            ts_ms: now_ms(),
+            action: "rotate_key",
+            tenant_id: &auth.tenant_id,
+            key_id: Some(&auth.key_id),
+            request_id,
+            file_id: None,
+            extra: serde_json::json!({
+                "previous_version": previous_version,
+                "new_version": new_version,
+                "files_rewrapped": files_rewrapped,
+                "blobs_rewrapped": blobs_rewrapped,
+            }),
+        },
+    )
+    .await;
+
+    Ok((
+        StatusCode::OK,
+        Json(RotateKeyResponse {
+            ok: true,
+            previous_version,
+            new_version,
+            files_rewrapped,
+            blobs_rewrapped,
+        }),
+    ))
+}
+
+#[derive(Debug, Deserialize)]
+struct MintTokenRequest {
+    /// Defaults to the minting key's own tenant if omitted; any other value
+    /// is rejected, since an admin mints tokens for their own tenant only.
+    tenant_id: Option<String>,
+    scopes: Vec<String>,
+    ttl_seconds: Option<u32>,
+}
+
+#[derive(Debug, Serialize)]
+struct MintTokenResponse {
+    ok: bool,
+    token: String,
+    tenant_id: String,
+    scopes: Vec<String>,
+    expires_at_ms: i64,
+}
+
+/// Mints a short-lived, scope-restricted bearer token (admin-role only).
+/// Unlike the long-lived keys `RUSTFS_API_KEYS_JSON`/`api_keys` hand out,
+/// this needs no DB row and nothing to revoke: the HMAC signature and
+/// `exp_ms` embedded in the token are the only things `auth_from_headers`
+/// checks, so least-privilege, auto-expiring credentials can be handed to a
+/// caller without touching the env JSON or restarting the process.
+async fn mint_token(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(req): Json<MintTokenRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let request_id = headers.get("x-request-id").and_then(|v| v.to_str().ok());
+    let auth = auth_from_headers(&state, &headers, None).await?;
+    assert_is_admin(&auth)?;
+
+    let signing_key = state.signing_key.as_deref().ok_or_else(|| {
+        AppError::InvalidRequest("RUSTFS_SIGNING_KEY is not configured".to_string())
+    })?;
+    if req.scopes.is_empty() {
+        return Err(AppError::InvalidRequest("scopes required".to_string()));
+    }
+    let tenant_id = req
+        .tenant_id
+        .as_deref()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| auth.tenant_id.clone());
+    // An admin mints tokens for their own tenant only; a request naming a
+    // different tenant doesn't get silently redirected to the caller's own
+    // tenant, it's rejected outright.
+    if tenant_id != auth.tenant_id {
+        return Err(AppError::Forbidden);
+    }
+
+    let ttl = req.ttl_seconds.unwrap_or(900).clamp(30, 86_400) as i64;
+    let expires_at_ms = now_ms() + ttl * 1000;
+    let payload = ScopedTokenPayload {
+        tenant_id: tenant_id.clone(),
+        scopes: req.scopes.clone(),
+        exp_ms: expires_at_ms,
+    };
+    let token = sign_scoped_token(signing_key, &payload)?;
+
+    append_audit(
+        &state,
+        AuditEntry {
+            id: uuid::Uuid::new_v4().to_string(),
+            ts_ms: now_ms(),
+            action: "token_mint",
+            tenant_id: &auth.tenant_id,
+            key_id: Some(&auth.key_id),
+            request_id,
+            file_id: None,
+            extra: serde_json::json!({ "scoped_tenant_id": tenant_id, "scopes": req.scopes }),
+        },
+    )
+    .await;
+
+    Ok((
+        StatusCode::OK,
+        Json(MintTokenResponse {
+            ok: true,
+            token,
+            tenant_id,
+            scopes: req.scopes,
+            expires_at_ms,
+        }),
+    ))
+}
+
+/// Parses `RUSTFS_API_KEYS_JSON` into a map keyed by the SHA-256 hash of
+/// each raw key, ready to hand to `StaticApiKeyProvider`. Only the hash is
+/// kept in memory past this point.
+fn parse_api_keys_json(raw: &str) -> HashMap<String, ApiKeyRecord> {
+    let trimmed = raw.trim();
+    if trimmed.is_empty() {
+        return HashMap::new();
+    }
+    let parsed: serde_json::Value = match serde_json::from_str(trimmed) {
+        Ok(v) => v,
+        Err(_) => return HashMap::new(),
+    };
+    let mut map = HashMap::new();
+    let arr = parsed.as_array().cloned().unwrap_or_default();
+    for item in arr {
+        let key = item.get("key").and_then(|v| v.as_str()).unwrap_or("").trim().to_string();
+        let tenant_id = item
+            .get("tenant_id")
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .trim()
+            .to_string();
+        let role = item.get("role").and_then(|v| v.as_str()).map(|s| s.to_string());
+        if key.is_empty() || tenant_id.is_empty() {
+            continue;
+        }
+        map.insert(
+            full_key_hash(&key),
+            ApiKeyRecord {
+                tenant_id,
+                role: normalize_role(role.as_deref()),
+                disabled: false,
+                expires_at_ms: None,
+            },
+        );
+    }
+    map
+}
+
+/// Parses `RUSTFS_TENANT_IDENTITIES_JSON`, a `{"tenant_id": "AGE-SECRET-KEY-1..."}`
+/// map of server-held age identities, used by `download`/`public_download`
+/// to decrypt files encrypted under `EncMethod::Recipients` when the caller
+/// doesn't supply its own identity via `X-Age-Identity`.
+fn parse_tenant_identities_json(raw: &str) -> HashMap<String, String> {
+    let trimmed = raw.trim();
+    if trimmed.is_empty() {
+        return HashMap::new();
+    }
+    let parsed: serde_json::Value = match serde_json::from_str(trimmed) {
+        Ok(v) => v,
+        Err(_) => return HashMap::new(),
+    };
+    let mut map = HashMap::new();
+    if let Some(obj) = parsed.as_object() {
+        for (tenant_id, identity) in obj {
+            if let Some(identity) = identity.as_str() {
+                map.insert(tenant_id.trim().to_string(), identity.trim().to_string());
+            }
+        }
+    }
+    map
+}
+
+#[tokio::main]
+async fn main() -> Result<(), anyhow::Error> {
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| "rustfs=info,tower_http=warn".into()),
+        )
+        .init();
+
+    let port: u16 = std::env::var("RUSTFS_PORT")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(8099);
+    let data_dir = std::env::var("RUSTFS_DATA_DIR").unwrap_or_else(|_| "/data".to_string());
+    let db_path = std::env::var("RUSTFS_DB_PATH").unwrap_or_else(|_| "/data/meta.db".to_string());
+    let require_api_key = std::env::var("RUSTFS_REQUIRE_API_KEY")
+        .ok()
+        .map(|v| v.trim().to_lowercase() == "true" || v.trim() == "1")
+        .unwrap_or(true);
+    let api_keys_json = std::env::var("RUSTFS_API_KEYS_JSON").unwrap_or_default();
+    let tenant_identities_json =
+        std::env::var("RUSTFS_TENANT_IDENTITIES_JSON").unwrap_or_default();
+    let tenant_pubkeys_json = std::env::var("RUSTFS_TENANT_PUBKEYS_JSON").unwrap_or_default();
+    let master_key_raw = std::env::var("RUSTFS_MASTER_KEY").ok().map(|v| v.trim().to_string());
+    let master_keys = master_key_raw
+        .as_deref()
+        .filter(|s| !s.is_empty())
+        .map(|s| Arc::new(tokio::sync::RwLock::new(MasterKeyRing::new(SecretString::from(s.to_string())))));
+    let signing_key = std::env::var("RUSTFS_SIGNING_KEY")
+        .ok()
+        .map(|v| v.trim().as_bytes().to_vec())
+        .filter(|v| !v.is_empty());
+    let public_base_url = std::env::var("RUSTFS_PUBLIC_BASE_URL")
+        .ok()
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty());
+    let audit_log_path = std::env::var("RUSTFS_AUDIT_LOG_PATH")
+        .ok()
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from);
+    let tls_cert_path = std::env::var("RUSTFS_TLS_CERT_PATH")
+        .ok()
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from);
+    let tls_key_path = std::env::var("RUSTFS_TLS_KEY_PATH")
+        .ok()
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from);
+    let cache_max_bytes: usize = std::env::var("RUSTFS_CACHE_MAX_BYTES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0);
+    let cache_max_entries: usize = std::env::var("RUSTFS_CACHE_MAX_ENTRIES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0);
+    let cors_allowed_origins = std::env::var("RUSTFS_CORS_ALLOWED_ORIGINS")
+        .ok()
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty());
+    let max_upload_bytes: Option<usize> = std::env::var("RUSTFS_MAX_UPLOAD_BYTES")
+        .ok()
+        .and_then(|v| v.trim().parse().ok());
+    let compression_enabled = std::env::var("RUSTFS_ENABLE_COMPRESSION")
+        .ok()
+        .map(|v| v.trim().to_lowercase() == "true" || v.trim() == "1")
+        .unwrap_or(false);
+
+    let data_dir = PathBuf::from(data_dir);
+    fs::create_dir_all(&data_dir).await?;
+    let storage_backend = storage::backend_from_env(&data_dir)
+        .await
+        .map_err(|e| anyhow::anyhow!(e.to_string()))?;
+    let db_path = PathBuf::from(db_path);
+    let static_keys = parse_api_keys_json(&api_keys_json);
+    let static_keys_empty = static_keys.is_empty();
+    let api_key_provider = auth_provider::CompositeApiKeyProvider::new(
+        auth_provider::SqliteApiKeyProvider::new(db_path.clone()),
+        auth_provider::StaticApiKeyProvider::new(static_keys),
+    );
+
+    let metrics_handle = PrometheusBuilder::new()
+        .install_recorder()
+        .map_err(|e| anyhow::anyhow!("failed to install metrics recorder: {e}"))?;
 
     let state = AppState {
-        data_dir: PathBuf::from(data_dir),
-        db_path: PathBuf::from(db_path),
+        data_dir,
+        db_path,
         require_api_key,
-        api_keys: Arc::new(parse_api_keys_json(&api_keys_json)),
-        master_key,
+        api_key_provider: Arc::new(api_key_provider),
+        master_keys,
         signing_key,
         public_base_url,
         audit_log_path,
+        storage: Arc::from(storage_backend),
+        tenant_identities: Arc::new(parse_tenant_identities_json(&tenant_identities_json)),
+        metrics_handle,
+        tenant_pubkeys: Arc::new(http_sig::parse_tenant_pubkeys_json(&tenant_pubkeys_json)),
+        download_cache: Arc::new(DownloadCache::new(cache_max_bytes, cache_max_entries)),
+        max_upload_bytes: max_upload_bytes.unwrap_or(DEFAULT_MAX_UPLOAD_BYTES),
     };
 
-    fs::create_dir_all(&state.data_dir).await?;
     init_db(&state.db_path).await.map_err(|e| anyhow::anyhow!(e.to_string()))?;
 
-    if state.require_api_key && state.api_keys.is_empty() {
-        warn!("RUSTFS_REQUIRE_API_KEY=true but RUSTFS_API_KEYS_JSON is empty; all requests will be unauthorized");
+    if state.require_api_key && static_keys_empty {
+        warn!(
+            "RUSTFS_REQUIRE_API_KEY=true but RUSTFS_API_KEYS_JSON is empty; requests need a key created via POST /v1/admin/api_keys"
+        );
     }
     info!(
         "rustfs starting: port={} data_dir={} db_path={} encryption={}",
         port,
         state.data_dir.display(),
         state.db_path.display(),
-        state.master_key.is_some()
+        state.master_keys.is_some()
     );
 
+    // `None` (the default) for each of these leaves the corresponding route
+    // chain exactly as it was before this middleware existed, so an
+    // unconfigured deployment's behavior doesn't change.
+    let cors_layer = cors_allowed_origins.map(|origins| {
+        if origins == "*" {
+            CorsLayer::new()
+                .allow_origin(Any)
+                .allow_methods(Any)
+                .allow_headers(Any)
+        } else {
+            let parsed: Vec<_> = origins
+                .split(',')
+                .filter_map(|o| o.trim().parse().ok())
+                .collect();
+            CorsLayer::new()
+                .allow_origin(parsed)
+                .allow_methods(Any)
+                .allow_headers(Any)
+        }
+    });
+    let body_limit_disable = max_upload_bytes.map(|_| DefaultBodyLimit::disable());
+    let body_limit_layer = max_upload_bytes.map(RequestBodyLimitLayer::new);
+    let compression_layer = compression_enabled.then(CompressionLayer::new);
+
     let app = Router::new()
         .route("/health", get(health))
         .route("/readyz", get(readyz))
-        .route("/v1/files", post(ingest).get(search))
+        .route("/metrics", get(metrics_handler))
+        .route(
+            "/v1/files",
+            post(ingest).get(search).layer(
+                ServiceBuilder::new()
+                    .option_layer(body_limit_disable)
+                    .option_layer(body_limit_layer)
+                    .option_layer(compression_layer.clone()),
+            ),
+        )
         .route("/v1/files/pending_extract", get(pending_extract))
-        .route("/v1/files/:file_id/meta", get(meta))
+        .route(
+            "/v1/files/:file_id/meta",
+            get(meta).layer(ServiceBuilder::new().option_layer(compression_layer)),
+        )
+        // Not wrapped in `compression_layer`: this route serves 206 Partial
+        // Content responses (see chunk1-1's Range support), and tower-http's
+        // `CompressionLayer` has no special case for them. Compressing a
+        // ranged response would label a gzip'd slice of the file with a
+        // `Content-Range` describing byte offsets into the *uncompressed*
+        // resource — a corrupt, undecodable response for any Range request.
         .route("/v1/files/:file_id", get(download))
         .route("/v1/files/:file_id/link", post(create_link))
+        .route(
+            "/v1/files/:file_id/links/:jti",
+            axum::routing::delete(revoke_link),
+        )
         .route("/v1/files/:file_id/annotations", post(upsert_annotations))
         .route("/v1/files/:file_id/extract_status", post(set_extract_status))
         .route("/v1/files/:file_id/tombstone", post(tombstone))
+        .route("/v1/files/:file_id/verify", post(verify_file))
         .route("/v1/public/download", get(public_download))
+        .route("/v1/admin/api_keys", post(create_api_key).get(list_api_keys))
+        .route("/v1/admin/api_keys/:key_id", axum::routing::delete(revoke_api_key))
+        .route(
+            "/v1/admin/tenants/:tenant_id/recipients",
+            post(add_tenant_recipient),
+        )
+        .route("/v1/admin/sweep_blobs", post(sweep_blobs))
+        .route("/v1/admin/rotate_key", post(rotate_key))
+        .route("/v1/tokens", post(mint_token))
+        .route_layer(middleware::from_fn_with_state(state.clone(), verify_http_signature))
+        .route_layer(middleware::from_fn(track_metrics))
         .layer(TraceLayer::new_for_http())
+        .layer(ServiceBuilder::new().option_layer(cors_layer))
         .with_state(state);
 
-    let listener = TcpListener::bind(("0.0.0.0", port)).await?;
-    axum::serve(listener, app).await?;
+    match (tls_cert_path, tls_key_path) {
+        (Some(cert_path), Some(key_path)) => {
+            let tls_config = tls::load_rustls_config(&cert_path, &key_path)
+                .await
+                .map_err(|e| anyhow::anyhow!("failed to load TLS cert/key: {e}"))?;
+            tls::spawn_reload_watcher(tls_config.clone(), cert_path, key_path);
+            let addr = SocketAddr::from(([0, 0, 0, 0], port));
+            info!("serving HTTPS on {addr}");
+            axum_server::bind_rustls(addr, tls_config)
+                .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+                .await?;
+        }
+        (None, None) => {
+            let listener = TcpListener::bind(("0.0.0.0", port)).await?;
+            axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await?;
+        }
+        _ => {
+            return Err(anyhow::anyhow!(
+                "RUSTFS_TLS_CERT_PATH and RUSTFS_TLS_KEY_PATH must both be set to enable TLS"
+            ));
+        }
+    }
     Ok(())
 }
 
+
+#[cfg(test)]
+mod scoped_token_tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_a_freshly_minted_token() {
+        let signing_key = b"test-signing-key";
+        let payload = ScopedTokenPayload {
+            tenant_id: "acme".to_string(),
+            scopes: vec!["download".to_string(), "search".to_string()],
+            exp_ms: now_ms() + 60_000,
+        };
+        let token = sign_scoped_token(signing_key, &payload).unwrap();
+        let verified = verify_scoped_token(signing_key, &token).unwrap();
+        assert_eq!(verified.tenant_id, "acme");
+        assert_eq!(verified.scopes, vec!["download".to_string(), "search".to_string()]);
+    }
+
+    #[test]
+    fn rejects_an_expired_token() {
+        let signing_key = b"test-signing-key";
+        let payload = ScopedTokenPayload {
+            tenant_id: "acme".to_string(),
+            scopes: vec!["download".to_string()],
+            exp_ms: now_ms() - 1,
+        };
+        let token = sign_scoped_token(signing_key, &payload).unwrap();
+        assert!(verify_scoped_token(signing_key, &token).is_err());
+    }
+
+    #[test]
+    fn rejects_a_token_verified_with_the_wrong_signing_key() {
+        let payload = ScopedTokenPayload {
+            tenant_id: "acme".to_string(),
+            scopes: vec!["download".to_string()],
+            exp_ms: now_ms() + 60_000,
+        };
+        let token = sign_scoped_token(b"key-one", &payload).unwrap();
+        assert!(verify_scoped_token(b"key-two", &token).is_err());
+    }
+
+    #[test]
+    fn rejects_a_token_with_a_tampered_payload() {
+        let signing_key = b"test-signing-key";
+        let payload = ScopedTokenPayload {
+            tenant_id: "acme".to_string(),
+            scopes: vec!["download".to_string()],
+            exp_ms: now_ms() + 60_000,
+        };
+        let token = sign_scoped_token(signing_key, &payload).unwrap();
+        let (payload_b64, sig_b64) = token.split_once('.').unwrap();
+        let tampered_payload: ScopedTokenPayload = serde_json::from_slice(
+            &URL_SAFE_NO_PAD.decode(payload_b64.as_bytes()).unwrap(),
+        )
+        .unwrap();
+        let mut tampered_payload = tampered_payload;
+        tampered_payload.scopes.push("admin".to_string());
+        let tampered_b64 = URL_SAFE_NO_PAD.encode(serde_json::to_vec(&tampered_payload).unwrap());
+        let tampered_token = format!("{tampered_b64}.{sig_b64}");
+        assert!(verify_scoped_token(signing_key, &tampered_token).is_err());
+    }
+
+    #[test]
+    fn assert_scope_allows_unscoped_static_keys_and_narrows_scoped_tokens() {
+        let unscoped = AuthContext {
+            tenant_id: "acme".to_string(),
+            role: "writer".to_string(),
+            key_id: "k1".to_string(),
+            scopes: None,
+        };
+        assert!(assert_scope(&unscoped, "anything").is_ok());
+
+        let scoped = AuthContext {
+            tenant_id: "acme".to_string(),
+            role: "writer".to_string(),
+            key_id: "k2".to_string(),
+            scopes: Some(vec!["ingest".to_string()]),
+        };
+        assert!(assert_scope(&scoped, "ingest").is_ok());
+        assert!(assert_scope(&scoped, "download").is_err());
+    }
+}
@@ -0,0 +1,400 @@
+//! Pluggable object storage backends.
+//!
+//! `ingest`/`download`/`tombstone` used to touch `data_dir` on the local
+//! filesystem directly. That made it impossible to run the service
+//! statelessly against an S3-compatible object store (Garage, MinIO, AWS).
+//! This module defines the `StorageBackend` trait the handlers talk to, plus
+//! a filesystem implementation (the previous behavior) and an S3-compatible
+//! one, selected via `RUSTFS_STORAGE_BACKEND` (see `backend_from_env`).
+//! `ingest`/`download`/`tombstone`/the blob and chunk stores all go through
+//! `AppState.storage: Arc<dyn StorageBackend>` already; `data_dir` itself is
+//! only still touched for pre-upload scratch files, which never leave local
+//! disk regardless of backend.
+
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use tokio::io::AsyncWriteExt;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::Stream;
+use tokio_util::io::ReaderStream;
+
+use crate::AppError;
+
+pub type ByteStream = Pin<Box<dyn Stream<Item = std::io::Result<Bytes>> + Send>>;
+
+/// Where an object lives within a backend, scoped to a tenant.
+///
+/// Backends are free to interpret `key` however suits them (a filesystem
+/// path component, an S3 object key suffix, ...); callers never construct
+/// paths themselves anymore.
+#[async_trait]
+pub trait StorageBackend: Send + Sync {
+    async fn put(&self, tenant_id: &str, key: &str, body: ByteStream) -> Result<u64, AppError>;
+    async fn get(&self, tenant_id: &str, key: &str) -> Result<ByteStream, AppError>;
+    async fn delete(&self, tenant_id: &str, key: &str) -> Result<(), AppError>;
+    async fn exists(&self, tenant_id: &str, key: &str) -> Result<bool, AppError>;
+
+    /// Streams `len` bytes starting at byte `start`, for HTTP Range support.
+    /// The default just streams the whole object and discards bytes outside
+    /// `[start, start+len)`; backends that can do better (a real seek, an
+    /// HTTP Range request) should override this.
+    async fn get_range(
+        &self,
+        tenant_id: &str,
+        key: &str,
+        start: u64,
+        len: u64,
+    ) -> Result<ByteStream, AppError> {
+        let stream = self.get(tenant_id, key).await?;
+        Ok(bound_stream(stream, start, len))
+    }
+}
+
+/// Wraps a byte stream to emit only `len` bytes starting at `skip`, by
+/// reading and discarding up to the offset. Shared by the default
+/// `StorageBackend::get_range` and by readers (chunk reassembly, decrypt)
+/// that can't seek directly.
+pub fn bound_stream(mut stream: ByteStream, mut skip: u64, mut remaining: u64) -> ByteStream {
+    use tokio_stream::StreamExt;
+
+    let (tx, rx) = tokio::sync::mpsc::channel::<std::io::Result<Bytes>>(8);
+    tokio::spawn(async move {
+        while remaining > 0 {
+            let mut piece = match stream.next().await {
+                Some(Ok(p)) => p,
+                Some(Err(e)) => {
+                    let _ = tx.send(Err(e)).await;
+                    return;
+                }
+                None => break,
+            };
+            if skip > 0 {
+                if (piece.len() as u64) <= skip {
+                    skip -= piece.len() as u64;
+                    continue;
+                }
+                piece = piece.split_off(skip as usize);
+                skip = 0;
+            }
+            if (piece.len() as u64) > remaining {
+                piece.truncate(remaining as usize);
+            }
+            remaining -= piece.len() as u64;
+            if tx.send(Ok(piece)).await.is_err() {
+                return;
+            }
+        }
+    });
+    Box::pin(ReceiverStream::new(rx))
+}
+
+/// The original behavior: objects live under `<data_dir>/objects/<tenant_id>/<key>`.
+pub struct FilesystemBackend {
+    root: PathBuf,
+}
+
+impl FilesystemBackend {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    fn object_path(&self, tenant_id: &str, key: &str) -> PathBuf {
+        self.root.join("objects").join(tenant_id).join(key)
+    }
+}
+
+#[async_trait]
+impl StorageBackend for FilesystemBackend {
+    async fn put(&self, tenant_id: &str, key: &str, mut body: ByteStream) -> Result<u64, AppError> {
+        use tokio_stream::StreamExt;
+
+        let path = self.object_path(tenant_id, key);
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let mut out = tokio::fs::File::create(&path).await?;
+        let mut written: u64 = 0;
+        while let Some(chunk) = body.next().await {
+            let chunk = chunk?;
+            out.write_all(&chunk).await?;
+            written += chunk.len() as u64;
+        }
+        out.flush().await?;
+        Ok(written)
+    }
+
+    async fn get(&self, tenant_id: &str, key: &str) -> Result<ByteStream, AppError> {
+        let path = self.object_path(tenant_id, key);
+        let file = tokio::fs::File::open(path)
+            .await
+            .map_err(|_| AppError::NotFound)?;
+        Ok(Box::pin(ReaderStream::new(file)))
+    }
+
+    async fn delete(&self, tenant_id: &str, key: &str) -> Result<(), AppError> {
+        let path = self.object_path(tenant_id, key);
+        match tokio::fs::remove_file(path).await {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn exists(&self, tenant_id: &str, key: &str) -> Result<bool, AppError> {
+        Ok(tokio::fs::try_exists(self.object_path(tenant_id, key)).await?)
+    }
+
+    async fn get_range(
+        &self,
+        tenant_id: &str,
+        key: &str,
+        start: u64,
+        len: u64,
+    ) -> Result<ByteStream, AppError> {
+        use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+        let path = self.object_path(tenant_id, key);
+        let mut file = tokio::fs::File::open(path)
+            .await
+            .map_err(|_| AppError::NotFound)?;
+        file.seek(std::io::SeekFrom::Start(start)).await?;
+        Ok(Box::pin(ReaderStream::new(file.take(len))))
+    }
+}
+
+/// Config for the S3/Garage-compatible backend, read from
+/// `RUSTFS_S3_*` env vars at startup.
+#[derive(Clone, Debug)]
+pub struct S3Config {
+    pub endpoint: String,
+    pub region: String,
+    pub bucket: String,
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    /// Prepended to `<tenant_id>/<key>` so multiple services can share a bucket.
+    pub key_prefix: String,
+}
+
+/// Streams objects to/from an S3-compatible bucket (AWS S3, MinIO, Garage).
+/// One bucket, with a `<key_prefix>/<tenant_id>/<key>` layout so a single
+/// bucket can serve every tenant.
+pub struct S3Backend {
+    client: aws_sdk_s3::Client,
+    bucket: String,
+    key_prefix: String,
+}
+
+impl S3Backend {
+    pub async fn new(cfg: S3Config) -> Result<Self, AppError> {
+        let creds = aws_sdk_s3::config::Credentials::new(
+            cfg.access_key_id,
+            cfg.secret_access_key,
+            None,
+            None,
+            "rustfs-static",
+        );
+        let conf = aws_sdk_s3::Config::builder()
+            .endpoint_url(cfg.endpoint)
+            .region(aws_sdk_s3::config::Region::new(cfg.region))
+            .credentials_provider(creds)
+            .force_path_style(true)
+            .build();
+        Ok(Self {
+            client: aws_sdk_s3::Client::from_conf(conf),
+            bucket: cfg.bucket,
+            key_prefix: cfg.key_prefix,
+        })
+    }
+
+    fn object_key(&self, tenant_id: &str, key: &str) -> String {
+        if self.key_prefix.is_empty() {
+            format!("{tenant_id}/{key}")
+        } else {
+            format!("{}/{tenant_id}/{key}", self.key_prefix.trim_end_matches('/'))
+        }
+    }
+}
+
+#[async_trait]
+impl StorageBackend for S3Backend {
+    async fn put(&self, tenant_id: &str, key: &str, mut body: ByteStream) -> Result<u64, AppError> {
+        use tokio_stream::StreamExt;
+
+        // Buffer in memory before a multipart upload; streams that exceed a
+        // single part are split into 8 MiB parts below.
+        const PART_SIZE: usize = 8 * 1024 * 1024;
+        let object_key = self.object_key(tenant_id, key);
+
+        let create = self
+            .client
+            .create_multipart_upload()
+            .bucket(&self.bucket)
+            .key(&object_key)
+            .send()
+            .await
+            .map_err(|e| AppError::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string())))?;
+        let upload_id = create
+            .upload_id()
+            .ok_or_else(|| AppError::Crypto("s3: missing upload_id".to_string()))?
+            .to_string();
+
+        let mut part_number = 1;
+        let mut parts = Vec::new();
+        let mut buf = Vec::with_capacity(PART_SIZE);
+        let mut total: u64 = 0;
+
+        macro_rules! flush_part {
+            () => {
+                if !buf.is_empty() {
+                    let body_bytes = std::mem::replace(&mut buf, Vec::with_capacity(PART_SIZE));
+                    let len = body_bytes.len() as u64;
+                    let resp = self
+                        .client
+                        .upload_part()
+                        .bucket(&self.bucket)
+                        .key(&object_key)
+                        .upload_id(&upload_id)
+                        .part_number(part_number)
+                        .body(body_bytes.into())
+                        .send()
+                        .await
+                        .map_err(|e| {
+                            AppError::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))
+                        })?;
+                    parts.push(
+                        aws_sdk_s3::types::CompletedPart::builder()
+                            .part_number(part_number)
+                            .e_tag(resp.e_tag().unwrap_or_default())
+                            .build(),
+                    );
+                    part_number += 1;
+                    total += len;
+                }
+            };
+        }
+
+        while let Some(chunk) = body.next().await {
+            let chunk = chunk?;
+            buf.extend_from_slice(&chunk);
+            if buf.len() >= PART_SIZE {
+                flush_part!();
+            }
+        }
+        flush_part!();
+
+        self.client
+            .complete_multipart_upload()
+            .bucket(&self.bucket)
+            .key(&object_key)
+            .upload_id(&upload_id)
+            .multipart_upload(
+                aws_sdk_s3::types::CompletedMultipartUpload::builder()
+                    .set_parts(Some(parts))
+                    .build(),
+            )
+            .send()
+            .await
+            .map_err(|e| AppError::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string())))?;
+
+        Ok(total)
+    }
+
+    async fn get(&self, tenant_id: &str, key: &str) -> Result<ByteStream, AppError> {
+        let object_key = self.object_key(tenant_id, key);
+        let resp = self
+            .client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(&object_key)
+            .send()
+            .await
+            .map_err(|_| AppError::NotFound)?;
+        let stream = resp
+            .body
+            .map(|r| r.map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string())));
+        Ok(Box::pin(stream))
+    }
+
+    async fn delete(&self, tenant_id: &str, key: &str) -> Result<(), AppError> {
+        let object_key = self.object_key(tenant_id, key);
+        self.client
+            .delete_object()
+            .bucket(&self.bucket)
+            .key(&object_key)
+            .send()
+            .await
+            .map_err(|e| AppError::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string())))?;
+        Ok(())
+    }
+
+    async fn get_range(
+        &self,
+        tenant_id: &str,
+        key: &str,
+        start: u64,
+        len: u64,
+    ) -> Result<ByteStream, AppError> {
+        let object_key = self.object_key(tenant_id, key);
+        let end = start + len.saturating_sub(1);
+        let resp = self
+            .client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(&object_key)
+            .range(format!("bytes={start}-{end}"))
+            .send()
+            .await
+            .map_err(|_| AppError::NotFound)?;
+        let stream = resp
+            .body
+            .map(|r| r.map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string())));
+        Ok(Box::pin(stream))
+    }
+
+    async fn exists(&self, tenant_id: &str, key: &str) -> Result<bool, AppError> {
+        let object_key = self.object_key(tenant_id, key);
+        match self
+            .client
+            .head_object()
+            .bucket(&self.bucket)
+            .key(&object_key)
+            .send()
+            .await
+        {
+            Ok(_) => Ok(true),
+            Err(e) if e.as_service_error().map(|e| e.is_not_found()).unwrap_or(false) => Ok(false),
+            Err(e) => Err(AppError::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))),
+        }
+    }
+}
+
+/// Builds the configured backend from env vars.
+///
+/// `RUSTFS_STORAGE_BACKEND=filesystem` (default) keeps the current
+/// `data_dir`-rooted layout; `RUSTFS_STORAGE_BACKEND=s3` reads
+/// `RUSTFS_S3_{ENDPOINT,REGION,BUCKET,ACCESS_KEY_ID,SECRET_ACCESS_KEY,KEY_PREFIX}`.
+pub async fn backend_from_env(data_dir: &Path) -> Result<Box<dyn StorageBackend>, AppError> {
+    let kind = std::env::var("RUSTFS_STORAGE_BACKEND").unwrap_or_else(|_| "filesystem".to_string());
+    match kind.trim() {
+        "" | "filesystem" => Ok(Box::new(FilesystemBackend::new(data_dir.to_path_buf()))),
+        "s3" => {
+            let cfg = S3Config {
+                endpoint: std::env::var("RUSTFS_S3_ENDPOINT")
+                    .map_err(|_| AppError::InvalidRequest("RUSTFS_S3_ENDPOINT is required".to_string()))?,
+                region: std::env::var("RUSTFS_S3_REGION").unwrap_or_else(|_| "us-east-1".to_string()),
+                bucket: std::env::var("RUSTFS_S3_BUCKET")
+                    .map_err(|_| AppError::InvalidRequest("RUSTFS_S3_BUCKET is required".to_string()))?,
+                access_key_id: std::env::var("RUSTFS_S3_ACCESS_KEY_ID").unwrap_or_default(),
+                secret_access_key: std::env::var("RUSTFS_S3_SECRET_ACCESS_KEY").unwrap_or_default(),
+                key_prefix: std::env::var("RUSTFS_S3_KEY_PREFIX").unwrap_or_default(),
+            };
+            Ok(Box::new(S3Backend::new(cfg).await?))
+        }
+        other => Err(AppError::InvalidRequest(format!(
+            "unknown RUSTFS_STORAGE_BACKEND: {other}"
+        ))),
+    }
+}
@@ -0,0 +1,67 @@
+//! TLS termination via rustls, with hot-reloadable certificates.
+//!
+//! `axum_server::tls_rustls::RustlsConfig` keeps its `rustls::ServerConfig`
+//! behind an `arc-swap`, so reloading it only ever swaps a pointer: new
+//! connections pick up the new certificate, connections already in flight
+//! keep using the one they started with. That's what lets an operator drop
+//! a renewed Let's Encrypt cert onto disk without restarting the process or
+//! dropping anything currently being served.
+
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use axum_server::tls_rustls::RustlsConfig;
+use tracing::{error, info, warn};
+
+/// How often to poll the cert file's mtime for a change, as a fallback for
+/// deployments that can't or don't send SIGHUP on renewal.
+const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Loads the initial TLS config from a PEM cert chain and private key.
+pub async fn load_rustls_config(cert_path: &Path, key_path: &Path) -> std::io::Result<RustlsConfig> {
+    RustlsConfig::from_pem_file(cert_path, key_path).await
+}
+
+/// Spawns a background task that reloads `config` from `cert_path`/`key_path`
+/// whenever the cert file's mtime changes or a SIGHUP is received, whichever
+/// comes first. Reload failures (e.g. an operator mid-write of a new cert)
+/// are logged and leave the previously-loaded config in place.
+pub fn spawn_reload_watcher(config: RustlsConfig, cert_path: PathBuf, key_path: PathBuf) {
+    tokio::spawn(async move {
+        let mut last_mtime = file_mtime(&cert_path).await;
+        let mut hangup = match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup()) {
+            Ok(signal) => Some(signal),
+            Err(e) => {
+                warn!("failed to install SIGHUP handler for TLS reload, falling back to polling only: {e}");
+                None
+            }
+        };
+
+        loop {
+            let changed = match &mut hangup {
+                Some(signal) => {
+                    tokio::select! {
+                        _ = signal.recv() => true,
+                        _ = tokio::time::sleep(POLL_INTERVAL) => file_mtime(&cert_path).await != last_mtime,
+                    }
+                }
+                None => {
+                    tokio::time::sleep(POLL_INTERVAL).await;
+                    file_mtime(&cert_path).await != last_mtime
+                }
+            };
+            if !changed {
+                continue;
+            }
+            match config.reload_from_pem_file(&cert_path, &key_path).await {
+                Ok(()) => info!("reloaded TLS certificate from {}", cert_path.display()),
+                Err(e) => error!("failed to reload TLS certificate from {}: {e}", cert_path.display()),
+            }
+            last_mtime = file_mtime(&cert_path).await;
+        }
+    });
+}
+
+async fn file_mtime(path: &Path) -> Option<SystemTime> {
+    tokio::fs::metadata(path).await.ok()?.modified().ok()
+}